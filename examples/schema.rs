@@ -3,7 +3,13 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use split_transfer::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use split_transfer::msg::{
+    AuditLogResponse, CallbackMsg, ConfigResponse, DisplayAmount, ExecuteMsg,
+    ExportBalancesResponse, FeeRateHistoryResponse, FullConfigResponse, HistoryResponse,
+    InstantiateMsg, MigrateMsg, PreviewWithdrawResponse, QueryMsg, RecipientsResponse,
+    RolesResponse, SimulateSplitResponse, SolvencyResponse, StatsResponse, StatusResponse, SudoMsg,
+    TotalFeesResponse, TotalLiabilitiesResponse, VestingInfoResponse, WithdrawResponse,
+};
 use split_transfer::state::State;
 
 fn main() {
@@ -15,5 +21,26 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(SudoMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
+    export_schema(&schema_for!(CallbackMsg), &out_dir);
     export_schema(&schema_for!(State), &out_dir);
+    export_schema(&schema_for!(PreviewWithdrawResponse), &out_dir);
+    export_schema(&schema_for!(WithdrawResponse), &out_dir);
+    export_schema(&schema_for!(StatsResponse), &out_dir);
+    export_schema(&schema_for!(ConfigResponse), &out_dir);
+    export_schema(&schema_for!(FullConfigResponse), &out_dir);
+    export_schema(&schema_for!(StatusResponse), &out_dir);
+    export_schema(&schema_for!(RolesResponse), &out_dir);
+    export_schema(&schema_for!(VestingInfoResponse), &out_dir);
+    export_schema(&schema_for!(SolvencyResponse), &out_dir);
+    export_schema(&schema_for!(HistoryResponse), &out_dir);
+    export_schema(&schema_for!(AuditLogResponse), &out_dir);
+    export_schema(&schema_for!(TotalFeesResponse), &out_dir);
+    export_schema(&schema_for!(DisplayAmount), &out_dir);
+    export_schema(&schema_for!(TotalLiabilitiesResponse), &out_dir);
+    export_schema(&schema_for!(ExportBalancesResponse), &out_dir);
+    export_schema(&schema_for!(FeeRateHistoryResponse), &out_dir);
+    export_schema(&schema_for!(SimulateSplitResponse), &out_dir);
+    export_schema(&schema_for!(RecipientsResponse), &out_dir);
 }
@@ -1,21 +1,68 @@
-use std::ops::Div;
-
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    coin, to_binary, BankMsg, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+    coin, to_binary, Api, BankMsg, Binary, Deps, DepsMut, Env, Event, MessageInfo, Order, Reply,
+    Response, StdError, StdResult, Storage, SubMsg, SubMsgResult, Timestamp, Uint128, WasmMsg,
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
+use cw_storage_plus::Bound;
+use semver::Version;
 
 use crate::error::ContractError;
-use crate::helpers::validate_and_extract_coin;
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
-use crate::state::{State, AMOUNTS, FEE, STATE};
+use crate::helpers::{
+    breakdown_after_fee, compute_fee, compute_split, format_decimals, validate_and_extract_coin,
+    validate_denom, validate_denom_format, SplitBreakdown,
+};
+use crate::msg::{
+    AuditLogResponse, CallbackMsg, ConfigResponse, DisplayAmount, ExecuteMsg,
+    ExportBalancesResponse, FeeRateHistoryResponse, FullConfigResponse, HistoryResponse,
+    InstantiateMsg, InvalidPolicy, MigrateMsg, PreviewWithdrawResponse, QueryMsg,
+    RecipientsResponse, RolesResponse, SimulateSplitResponse, SolvencyResponse, StatsResponse,
+    StatusResponse, SudoMsg, TotalFeesResponse, TotalLiabilitiesResponse, VestingInfoResponse,
+    VestingTerms, WithdrawResponse,
+};
+use crate::state::{
+    AuditEntry, Config, Deposit, DepositRecord, Expiry, Lock, PendingPayout, PendingWithdrawal,
+    RoundingMode, State, Vesting, WindowWithdrawal, ADMINS, ALIASES, ALLOWED_DENOMS, AMOUNTS,
+    AUDIT_COUNT, AUDIT_LOG, BATCH_PAYOUTS, BLOCKED, CALLBACKS, CONFIG, COUPONS, DECIMALS,
+    DELEGATES, DENOM_FEE_BPS, DEPOSITS, DUST, EXPIRIES, FEE_AUTO_FORWARD_THRESHOLD, FEE_BY_DENOM,
+    FEE_OVERRIDES, FEE_RATE_HISTORY, FINALIZED, FLUSHED_PAYOUTS, HISTORY, IMPORTED,
+    LAST_WITHDRAW_BLOCK, LOCKS, MAP_SIZE, MAX_DEPOSIT, MAX_RECIPIENTS, MIN_FEE, MIN_WITHDRAWAL,
+    NONCES, PAUSED, PAYOUT_COUNT, PENDING_OWNER, PENDING_PAYOUTS, PENDING_WITHDRAWAL,
+    RECIPIENT_COUNT, RECLAIM_WINDOW, REFERRER_BPS, SEEN_KEYS, SENDER_ALLOWLIST, SENDER_RECIPIENTS,
+    SPLIT_COUNT, STATE, TOTAL_EARNED, TOTAL_LIABILITIES, TRACK_DUST, VESTING, WITHDRAWALS_FROZEN,
+    WITHDRAWN, WITHDRAW_COOLDOWN, WITHDRAW_LIMIT, WITHDRAW_WINDOW,
+};
 use cosmwasm_std::{Addr, Coin};
 
 const CONTRACT_NAME: &str = "crates.io:split-transfer";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+const WITHDRAW_REPLY_ID: u64 = 1;
+const WITHDRAW_FEES_REPLY_ID: u64 = 2;
+// the reply to the send of a flushed payout carries the payout id above this offset
+const PAYOUT_REPLY_ID_OFFSET: u64 = 1 << 32;
+
+const MAX_MEMO_LENGTH: usize = 256;
+
+const DEFAULT_DENOM: &str = "usei";
+const DEFAULT_FEE_BPS: u16 = 100;
+const DEFAULT_DECIMALS: u8 = 6;
+
+const MAX_BATCH_ADDRESSES: usize = 50;
+
+const DEFAULT_MAX_RECIPIENTS: u32 = 20;
+const MAX_RECIPIENTS_CAP: u32 = 50;
+
+// bounds the expiring or locked parts a single balance can be made of
+const MAX_PARTS: usize = 20;
+
+const DEFAULT_HISTORY_LIMIT: u32 = 10;
+const MAX_HISTORY_LIMIT: u32 = 50;
+
+const DEFAULT_FLUSH_LIMIT: u32 = 10;
+const MAX_FLUSH_LIMIT: u32 = 50;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -27,11 +74,74 @@ pub fn instantiate(
         owner: info.sender.clone(),
     };
 
-    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    let config = Config {
+        denom: msg.denom.unwrap_or_else(|| DEFAULT_DENOM.to_string()),
+        fee_bps: msg.fee_bps.unwrap_or(DEFAULT_FEE_BPS),
+        min_amount: msg.min_amount.unwrap_or_default(),
+        fee_recipient: match msg.fee_recipient {
+            Some(fee_recipient) => deps.api.addr_validate(fee_recipient.as_str())?,
+            None => info.sender.clone(),
+        },
+        fee_rounding: msg.fee_rounding.unwrap_or(RoundingMode::Floor),
+    };
+    if config.fee_bps > 10_000 {
+        return Err(ContractError::InvalidFeeBps {});
+    }
+    if msg.require_distinct_fee_recipient && config.fee_recipient == state.owner {
+        return Err(ContractError::OwnerIsFeeRecipient {});
+    }
+
+    let allowed_denoms = msg
+        .allowed_denoms
+        .unwrap_or_else(|| vec![config.denom.clone()]);
+    validate_denom_format(&config.denom)?;
+    for denom in &allowed_denoms {
+        validate_denom_format(denom)?;
+    }
+
+    // store the version normalized, migrate has to compare it
+    let version = parse_version(CONTRACT_VERSION)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, version.to_string())?;
 
-    // save the state and initialize fee
+    // save the state and initialize split count
     STATE.save(deps.storage, &state)?;
-    FEE.save(deps.storage, &0)?;
+    CONFIG.save(deps.storage, &config)?;
+    ALLOWED_DENOMS.save(deps.storage, &allowed_denoms)?;
+    SENDER_ALLOWLIST.save(deps.storage, &None)?;
+    SPLIT_COUNT.save(deps.storage, &0)?;
+    AUDIT_COUNT.save(deps.storage, &0)?;
+    RECIPIENT_COUNT.save(deps.storage, &0)?;
+    MAP_SIZE.save(deps.storage, &0)?;
+    PAUSED.save(deps.storage, &msg.start_paused)?;
+    WITHDRAWALS_FROZEN.save(deps.storage, &false)?;
+    ADMINS.save(deps.storage, &vec![])?;
+    BATCH_PAYOUTS.save(deps.storage, &msg.batch_payouts)?;
+    TRACK_DUST.save(deps.storage, &msg.track_dust)?;
+    DECIMALS.save(deps.storage, &msg.decimals.unwrap_or(DEFAULT_DECIMALS))?;
+    PAYOUT_COUNT.save(deps.storage, &0)?;
+    FINALIZED.save(deps.storage, &false)?;
+    MIN_FEE.save(deps.storage, &msg.min_fee.unwrap_or_default())?;
+    REFERRER_BPS.save(deps.storage, &0)?;
+    RECLAIM_WINDOW.save(deps.storage, &msg.reclaim_window)?;
+    WITHDRAW_LIMIT.save(deps.storage, &None)?;
+    WITHDRAW_WINDOW.save(deps.storage, &0)?;
+    WITHDRAW_COOLDOWN.save(deps.storage, &0)?;
+    MIN_WITHDRAWAL.save(deps.storage, &Uint128::zero())?;
+    MAX_RECIPIENTS.save(deps.storage, &DEFAULT_MAX_RECIPIENTS)?;
+    FEE_AUTO_FORWARD_THRESHOLD.save(deps.storage, &None)?;
+    MAX_DEPOSIT.save(deps.storage, &None)?;
+
+    // seed the initial balances in the default denom
+    for (address, amount) in msg.initial_balances {
+        let address = deps.api.addr_validate(address.as_str())?;
+        let key = (address, config.denom.clone());
+        if AMOUNTS.has(deps.storage, key.clone()) {
+            return Err(ContractError::DuplicateInitialBalance {
+                address: key.0.into(),
+            });
+        }
+        credit(deps.storage, &key.0, &key.1, amount)?;
+    }
 
     Ok(Response::new()
         .add_attribute("method", "instantiate")
@@ -41,354 +151,10859 @@ pub fn instantiate(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
-    info: MessageInfo,
+    env: Env,
+    mut info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
+    // a zero-amount coin carries nothing, so no handler sees it
+    info.funds.retain(|sent| !sent.amount.is_zero());
+
     match msg {
         ExecuteMsg::Split {
             recipient1,
             recipient2,
-        } => split(deps, info, recipient1, recipient2),
-        ExecuteMsg::Withdraw { quantity } => withdraw(deps, info, quantity),
-        ExecuteMsg::WithdrawFees {} => withdraw_fees(deps, info),
+            memo,
+            idempotency_key,
+            vesting,
+            expires_at,
+            fee_on_top,
+            coupon,
+            nonce,
+            lock_until,
+            referrer,
+        } => split(
+            deps,
+            env,
+            info,
+            recipient1,
+            recipient2,
+            memo,
+            idempotency_key,
+            vesting,
+            expires_at,
+            fee_on_top,
+            coupon,
+            nonce,
+            lock_until,
+            referrer,
+        ),
+        ExecuteMsg::SplitWithPlatform {
+            recipient1,
+            recipient2,
+            platform,
+            platform_bps,
+        } => split_with_platform(
+            deps,
+            env,
+            info,
+            recipient1,
+            recipient2,
+            platform,
+            platform_bps,
+        ),
+        ExecuteMsg::WithdrawAndSplit {
+            recipient1,
+            recipient2,
+        } => withdraw_and_split(deps, env, info, recipient1, recipient2),
+        ExecuteMsg::SplitExact {
+            payouts,
+            on_invalid,
+        } => split_exact(deps, env, info, payouts, on_invalid),
+        ExecuteMsg::SplitTip {
+            tip_recipient,
+            tip_bps,
+        } => split_tip(deps, env, info, tip_recipient, tip_bps),
+        ExecuteMsg::SplitByAlias {
+            recipient1,
+            recipient1_alias,
+            recipient2,
+            recipient2_alias,
+        } => split_by_alias(
+            deps,
+            env,
+            info,
+            (recipient1, recipient1_alias),
+            (recipient2, recipient2_alias),
+        ),
+        ExecuteMsg::SplitKeepHalf { recipient } => split_keep_half(deps, env, info, recipient),
+        ExecuteMsg::Donate {} => donate(deps, env, info),
+        ExecuteMsg::Withdraw {
+            quantity,
+            denom,
+            percent,
+            from,
+            to,
+        } => withdraw(deps, env, info, quantity, denom, percent, from, to),
+        ExecuteMsg::WithdrawAll {} => withdraw_all(deps, env, info),
+        ExecuteMsg::WithdrawSplit { destinations } => withdraw_split(deps, env, info, destinations),
+        ExecuteMsg::FlushPayouts { limit } => flush_payouts(deps, info, limit),
+        ExecuteMsg::CancelPendingPayout {} => cancel_pending_payout(deps, info),
+        ExecuteMsg::RegisterAlias { alias, address } => register_alias(deps, info, alias, address),
+        ExecuteMsg::RegisterCallback { contract } => register_callback(deps, info, contract),
+        ExecuteMsg::SetWithdrawDelegate { delegate } => set_withdraw_delegate(deps, info, delegate),
+        ExecuteMsg::WithdrawDenoms { denoms } => withdraw_denoms(deps, env, info, denoms),
+        ExecuteMsg::WithdrawDust {} => withdraw_dust(deps, env, info),
+        ExecuteMsg::WithdrawFees { amount, denom } => withdraw_fees(deps, env, info, amount, denom),
+        ExecuteMsg::WithdrawFeesSplit { recipients } => {
+            withdraw_fees_split(deps, env, info, recipients)
+        }
+        ExecuteMsg::Reclaim {} => reclaim(deps, env, info),
+        ExecuteMsg::SetWithdrawLimit { limit, window } => {
+            set_withdraw_limit(deps, env, info, limit, window)
+        }
+        ExecuteMsg::SetWithdrawCooldown { blocks } => {
+            set_withdraw_cooldown(deps, env, info, blocks)
+        }
+        ExecuteMsg::SetFeeOverride { sender, fee_bps } => {
+            set_fee_override(deps, env, info, sender, Some(fee_bps))
+        }
+        ExecuteMsg::ClearFeeOverride { sender } => set_fee_override(deps, env, info, sender, None),
+        ExecuteMsg::SetDenomFee { denom, fee_bps } => {
+            set_denom_fee(deps, env, info, denom, fee_bps)
+        }
+        ExecuteMsg::SetReferrerBps { referrer_bps } => {
+            set_referrer_bps(deps, env, info, referrer_bps)
+        }
+        ExecuteMsg::CreateCoupon { code, fee_bps } => create_coupon(deps, env, info, code, fee_bps),
+        ExecuteMsg::SetMinWithdrawal { min } => set_min_withdrawal(deps, env, info, min),
+        ExecuteMsg::SetMaxRecipients { max } => set_max_recipients(deps, env, info, max),
+        ExecuteMsg::SetMaxDeposit { max } => set_max_deposit(deps, env, info, max),
+        ExecuteMsg::SetFeeAutoForward { threshold } => {
+            set_fee_auto_forward(deps, env, info, threshold)
+        }
+        ExecuteMsg::SetPaused { paused } => set_paused(deps, env, info, paused),
+        ExecuteMsg::SetWithdrawalsFrozen { frozen } => {
+            set_withdrawals_frozen(deps, env, info, frozen)
+        }
+        ExecuteMsg::SetFeeRate { fee_bps } => set_fee_rate(deps, env, info, fee_bps),
+        ExecuteMsg::AddAdmin { admin } => add_admin(deps, env, info, admin),
+        ExecuteMsg::RemoveAdmin { admin } => remove_admin(deps, env, info, admin),
+        ExecuteMsg::ReconcileFees {} => reconcile_fees(deps, env, info),
+        ExecuteMsg::EmergencySweep { to } => emergency_sweep(deps, env, info, to),
+        ExecuteMsg::ForceWithdraw { recipient, to } => {
+            force_withdraw(deps, env, info, recipient, to)
+        }
+        ExecuteMsg::AddDenom { denom } => add_denom(deps, env, info, denom),
+        ExecuteMsg::RemoveDenom { denom } => remove_denom(deps, env, info, denom),
+        ExecuteMsg::AddSender { sender } => add_sender(deps, env, info, sender),
+        ExecuteMsg::RemoveSender { sender } => remove_sender(deps, env, info, sender),
+        ExecuteMsg::BlockAddress { address } => set_blocked(deps, env, info, address, true),
+        ExecuteMsg::UnblockAddress { address } => set_blocked(deps, env, info, address, false),
+        ExecuteMsg::PruneZeroBalances { limit } => prune_zero_balances(deps, env, info, limit),
+        ExecuteMsg::ImportBalances { entries } => import_balances(deps, env, info, entries),
+        ExecuteMsg::SweepExpired { recipient } => sweep_expired(deps, env, info, recipient),
+        ExecuteMsg::Finalize {} => finalize(deps, env, info),
+        ExecuteMsg::ProposeOwner { owner } => propose_owner(deps, env, info, owner),
+        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, env, info),
+        ExecuteMsg::CancelOwnershipProposal {} => cancel_ownership_proposal(deps, env, info),
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn split(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     recipient1: Addr,
     recipient2: Addr,
+    memo: Option<String>,
+    idempotency_key: Option<String>,
+    vesting: Option<VestingTerms>,
+    expires_at: Option<Timestamp>,
+    fee_on_top: bool,
+    coupon: Option<String>,
+    nonce: Option<u64>,
+    lock_until: Option<u64>,
+    referrer: Option<Addr>,
 ) -> Result<Response, ContractError> {
-    let sent_coin = validate_and_extract_coin(&info.funds)?;
+    // reject empty recipients before they reach the address validation
+    if recipient1.as_str().trim().is_empty() || recipient2.as_str().trim().is_empty() {
+        return Err(ContractError::InvalidRecipient {});
+    }
+    let recipient1 = normalize_addr(deps.api, &recipient1)?;
+    let recipient2 = normalize_addr(deps.api, &recipient2)?;
+    let config = prepare_split(deps.as_ref(), &env, &info, &[&recipient1, &recipient2])?;
+
+    // check if the memo is within the limit, it is not stored
+    if let Some(memo) = &memo {
+        if memo.len() > MAX_MEMO_LENGTH {
+            return Err(ContractError::MemoTooLong {
+                max: MAX_MEMO_LENGTH,
+            });
+        }
+    }
+
+    // validate the vesting, it cannot replace one still locking a recipient's balance
+    if let Some(terms) = &vesting {
+        if terms.immediate_bps > 10_000 || terms.duration == 0 || terms.cliff > terms.duration {
+            return Err(ContractError::InvalidVesting {});
+        }
+        for recipient in [&recipient1, &recipient2] {
+            for sent_coin in &info.funds {
+                let key = (recipient.clone(), sent_coin.denom.clone());
+                if let Some(active) = VESTING.may_load(deps.storage, key)? {
+                    if !locked_amount(&active, &env).is_zero() {
+                        return Err(ContractError::VestingActive {});
+                    }
+                }
+            }
+        }
+    }
 
-    // collect 1% as fee and store it
-    let fee = sent_coin.amount.u128().div(100);
-    let total_fee = FEE.load(deps.storage)? + fee;
-    FEE.save(deps.storage, &total_fee)?;
+    if let Some(expires_at) = expires_at {
+        if expires_at <= env.block.time {
+            return Err(ContractError::InvalidExpiry {});
+        }
+    }
 
-    // split the amount into two
-    let split_amount = (sent_coin.amount.u128() - fee) / 2;
+    if let Some(lock_until) = lock_until {
+        if lock_until <= env.block.height {
+            return Err(ContractError::InvalidLockHeight {});
+        }
+    }
 
-    // if balance already present, update it or else, initialize
-    let amount = |d: Option<u128>| -> StdResult<u128> {
-        match d {
-            Some(old_amount) => Ok(old_amount + split_amount),
-            None => Ok(split_amount.clone()),
+    // the referrer has to be a third party the contract can pay out
+    let referrer = match referrer {
+        Some(referrer) => {
+            let referrer = normalize_addr(deps.api, &referrer)?;
+            let parties = [
+                &env.contract.address,
+                &info.sender,
+                &recipient1,
+                &recipient2,
+            ];
+            if parties.contains(&&referrer) {
+                return Err(ContractError::InvalidReferrer {});
+            }
+            check_not_blocked(deps.storage, &referrer)?;
+            Some(referrer)
         }
+        None => None,
     };
 
-    AMOUNTS.update(deps.storage, recipient1.clone(), amount)?;
-    AMOUNTS.update(deps.storage, recipient2.clone(), amount)?;
+    // the coupon must exist, it is only used up once the split is accepted
+    let coupon = match coupon {
+        Some(code) => match COUPONS.may_load(deps.storage, code.clone())? {
+            Some(fee_bps) => Some((code, fee_bps)),
+            None => return Err(ContractError::InvalidCoupon {}),
+        },
+        None => None,
+    };
 
-    Ok(Response::new().add_attribute("method", "split"))
-}
+    // reject a stale or skipped nonce
+    if let Some(nonce) = nonce {
+        let expected = NONCES
+            .may_load(deps.storage, info.sender.clone())?
+            .unwrap_or_default();
+        if nonce != expected {
+            return Err(ContractError::InvalidNonce { expected });
+        }
+        NONCES.save(deps.storage, info.sender.clone(), &(expected + 1))?;
+    }
 
-fn withdraw_fees(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
-    // check if the sender is the owner
-    if info.sender != STATE.load(deps.storage)?.owner {
-        return Err(ContractError::NotOwner {});
+    // reject a resubmitted split, keys are scoped per sender
+    if let Some(key) = idempotency_key {
+        let key = (info.sender.clone(), key);
+        if SEEN_KEYS.has(deps.storage, key.clone()) {
+            return Err(ContractError::DuplicateSplit {});
+        }
+        SEEN_KEYS.save(deps.storage, key, &())?;
     }
 
-    // fetch the collected fee and transfer it to the owner
-    let amount = FEE.load(deps.storage)?;
-    FEE.save(deps.storage, &0)?;
-    return Ok(send_tokens(
-        info.sender.clone(),
-        vec![coin(amount.into(), "usei")],
-        "withdraw",
-    ));
+    // apply the fee and split every sent coin separately
+    let threshold = FEE_AUTO_FORWARD_THRESHOLD.load(deps.storage)?;
+    let mut min_fee = MIN_FEE.load(deps.storage)?;
+    let mut coupon_bps = None;
+    if let Some((code, fee_bps)) = coupon {
+        COUPONS.remove(deps.storage, code);
+        coupon_bps = Some(fee_bps);
+        min_fee = Uint128::zero();
+    }
+    let referrer_bps = REFERRER_BPS.load(deps.storage)?;
+    let mut split_amounts = vec![];
+    let mut fees = vec![];
+    let mut total_fees = vec![];
+    let mut referrals = vec![];
+    let mut forwarded = vec![];
+    for sent_coin in info.funds.iter().cloned() {
+        // split the amount after the fee of the denom, or of the coupon, into two
+        let mut coin_config = denom_config(deps.storage, &config, &info.sender, &sent_coin.denom)?;
+        if let Some(fee_bps) = coupon_bps {
+            coin_config.fee_bps = fee_bps;
+        }
+        let shares = if fee_on_top {
+            compute_split_fee_on_top(sent_coin.amount, &coin_config, min_fee)?
+        } else {
+            compute_split(sent_coin.amount.u128(), &coin_config, min_fee.u128(), 2)
+        };
+        let split_amount = Uint128::new(check_split(shares)?.amount_each);
+        let fee = take_dust(deps.storage, &sent_coin.denom, &shares)?;
+        fees.push(coin(fee.u128(), &sent_coin.denom));
+
+        // the referrer takes its cut out of the fee
+        let mut referral = Uint128::zero();
+        if let Some(referrer) = &referrer {
+            referral = fee.multiply_ratio(referrer_bps, 10_000u128);
+            if !referral.is_zero() {
+                credit_split(deps.storage, referrer, &sent_coin.denom, referral)?;
+            }
+            referrals.push(coin(referral.u128(), &sent_coin.denom).to_string());
+        }
+        let mut total_fee = add_fee(deps.storage, &sent_coin.denom, fee - referral)?;
+
+        // forward the fee collected above the threshold
+        if let Some(threshold) = threshold {
+            if total_fee > threshold {
+                forwarded.push(coin((total_fee - threshold).u128(), &sent_coin.denom));
+                FEE_BY_DENOM.save(deps.storage, sent_coin.denom.clone(), &threshold)?;
+                total_fee = threshold;
+            }
+        }
+        total_fees.push(coin(total_fee.u128(), &sent_coin.denom).to_string());
+
+        credit_split(deps.storage, &recipient1, &sent_coin.denom, split_amount)?;
+        credit_split(deps.storage, &recipient2, &sent_coin.denom, split_amount)?;
+
+        for recipient in [&recipient1, &recipient2] {
+            let key = (recipient.clone(), sent_coin.denom.clone());
+
+            // only the share of this split expires
+            if let Some(expires_at) = expires_at {
+                add_expiry(deps.storage, key.clone(), split_amount, expires_at)?;
+            }
+
+            // nor is anything but the share of this split locked
+            if let Some(lock_until) = lock_until {
+                add_lock(deps.storage, &env, key, split_amount, lock_until)?;
+            }
+        }
+
+        // lock the part of each share that is not withdrawable immediately
+        if let Some(terms) = &vesting {
+            let immediate = split_amount.multiply_ratio(terms.immediate_bps, 10_000u128);
+            let vesting = Vesting {
+                amount: split_amount - immediate,
+                start: env.block.time.seconds(),
+                cliff: terms.cliff,
+                duration: terms.duration,
+            };
+            for recipient in [&recipient1, &recipient2] {
+                let key = (recipient.clone(), sent_coin.denom.clone());
+                VESTING.save(deps.storage, key, &vesting)?;
+            }
+        }
+        split_amounts.push(coin(split_amount.u128(), sent_coin.denom));
+    }
+
+    // notify the recipients that registered a callback, the messages only
+    // run once every state update of the split is done
+    let mut callbacks = vec![];
+    for recipient in [&recipient1, &recipient2] {
+        if let Some(contract) = CALLBACKS.may_load(deps.storage, recipient.clone())? {
+            callbacks.push(WasmMsg::Execute {
+                contract_addr: contract.into(),
+                msg: to_binary(&CallbackMsg {
+                    credited: split_amounts.clone(),
+                })?,
+                funds: vec![],
+            });
+        }
+    }
+
+    let event = action_event("split", &info.sender, &info.funds);
+    audit(deps.storage, &env, "split", &info.sender, &info.funds)?;
+    let recipients = vec![recipient1.clone(), recipient2.clone()];
+    record_split(
+        deps.storage,
+        &env,
+        &info.sender,
+        recipients,
+        info.funds,
+        fees,
+    )?;
+
+    // remember the deposit so the sender can reclaim it
+    if RECLAIM_WINDOW.load(deps.storage)?.is_some() {
+        let deposit = Deposit {
+            recipient1,
+            recipient2,
+            amount: split_amounts,
+            height: env.block.height,
+            time: env.block.time,
+            vesting: vesting.is_some(),
+        };
+        DEPOSITS.save(deps.storage, info.sender, &deposit)?;
+    }
+
+    let mut res = Response::new()
+        .add_event(event)
+        .add_messages(callbacks)
+        .add_attribute("method", "split")
+        .add_attribute("total_fee", total_fees.join(","));
+    if let Some(memo) = memo {
+        res = res.add_attribute("memo", memo);
+    }
+    if let Some(referrer) = referrer {
+        res = res
+            .add_attribute("referrer", referrer)
+            .add_attribute("referral", referrals.join(","));
+    }
+    if !forwarded.is_empty() {
+        res = res.add_message(BankMsg::Send {
+            to_address: config.fee_recipient.into(),
+            amount: forwarded,
+        });
+    }
+    Ok(res)
 }
 
-fn withdraw(
+fn split_with_platform(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    quantity: Option<u128>,
+    recipient1: Addr,
+    recipient2: Addr,
+    platform: Addr,
+    platform_bps: u16,
 ) -> Result<Response, ContractError> {
-    let amount = AMOUNTS.load(deps.storage, info.sender.clone())?;
+    // reject empty recipients before they reach the address validation
+    for recipient in [&recipient1, &recipient2, &platform] {
+        if recipient.as_str().trim().is_empty() {
+            return Err(ContractError::InvalidRecipient {});
+        }
+    }
+    let recipient1 = normalize_addr(deps.api, &recipient1)?;
+    let recipient2 = normalize_addr(deps.api, &recipient2)?;
+    let platform = normalize_addr(deps.api, &platform)?;
+    let recipients = [&recipient1, &recipient2, &platform];
+    let config = prepare_split(deps.as_ref(), &env, &info, &recipients)?;
+    let mut coin_configs = vec![];
+    for sent_coin in &info.funds {
+        let coin_config = denom_config(deps.storage, &config, &info.sender, &sent_coin.denom)?;
+        if u32::from(coin_config.fee_bps) + u32::from(platform_bps) > 10_000 {
+            return Err(ContractError::InvalidPlatformBps {});
+        }
+        coin_configs.push(coin_config);
+    }
 
-    // check if quantity is present
-    if let Some(quantity) = quantity {
-        // check if quantity is valid
-        if quantity > amount {
-            return Err(ContractError::ExceededQuantity {});
-        } else {
-            // update the store and send the tokens
-            AMOUNTS.save(deps.storage, info.sender.clone(), &(amount - quantity))?;
-            return Ok(send_tokens(
-                info.sender.clone(),
-                vec![coin(quantity.into(), "usei")],
-                "withdraw",
-            ));
+    let min_fee = MIN_FEE.load(deps.storage)?;
+    let mut fees = vec![];
+    let mut total_fees = vec![];
+    for (sent_coin, config) in info.funds.iter().zip(coin_configs) {
+        // carve out the fee and the platform cut, then split the rest into two
+        let platform_amount = sent_coin.amount.multiply_ratio(platform_bps, 10_000u128);
+        let fee = compute_fee(sent_coin.amount, &config)
+            .max(min_fee)
+            .min(sent_coin.amount - platform_amount);
+        let shares =
+            breakdown_after_fee((sent_coin.amount - platform_amount).u128(), fee.u128(), 2);
+        let split_amount = Uint128::new(check_split(shares)?.amount_each);
+        let fee = take_dust(deps.storage, &sent_coin.denom, &shares)?;
+        fees.push(coin(fee.u128(), &sent_coin.denom));
+        let total_fee = add_fee(deps.storage, &sent_coin.denom, fee)?;
+        total_fees.push(coin(total_fee.u128(), &sent_coin.denom).to_string());
+
+        // a zero cut does not open a balance for the platform
+        if !platform_amount.is_zero() {
+            credit_split(deps.storage, &platform, &sent_coin.denom, platform_amount)?;
         }
+        credit_split(deps.storage, &recipient1, &sent_coin.denom, split_amount)?;
+        credit_split(deps.storage, &recipient2, &sent_coin.denom, split_amount)?;
+    }
+
+    audit(
+        deps.storage,
+        &env,
+        "split_with_platform",
+        &info.sender,
+        &info.funds,
+    )?;
+    let recipients = vec![recipient1, recipient2, platform.clone()];
+    record_split(
+        deps.storage,
+        &env,
+        &info.sender,
+        recipients,
+        info.funds,
+        fees,
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("method", "split_with_platform")
+        .add_attribute("platform", platform)
+        .add_attribute("total_fee", total_fees.join(",")))
+}
+
+fn withdraw_and_split(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient1: Addr,
+    recipient2: Addr,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let withdrawal = validate_withdrawal(deps.as_ref(), &env, &info.sender, None, None, None)?;
+    let withdrawn_coin = withdrawal.amount[0].clone();
+
+    // take the balance, keeping any part still vesting
+    let key = (info.sender.clone(), withdrawn_coin.denom.clone());
+    check_withdraw_limit(deps.storage, &env, &key, withdrawn_coin.amount)?;
+    LAST_WITHDRAW_BLOCK.save(deps.storage, info.sender.clone(), &env.block.height)?;
+    if withdrawal.remaining.is_zero() {
+        remove_balance(deps.storage, key)?;
     } else {
-        // update the store and send the tokens
-        AMOUNTS.remove(deps.storage, info.sender.clone());
-        Ok(send_tokens(
-            info.sender.clone(),
-            vec![coin(amount.into(), "usei")],
-            "withdraw",
+        set_balance(deps.storage, key, withdrawal.remaining)?;
+    }
+
+    // split it as if it had been sent along
+    let info = MessageInfo {
+        sender: info.sender,
+        funds: vec![withdrawn_coin.clone()],
+    };
+    Ok(split(
+        deps, env, info, recipient1, recipient2, None, None, None, None, false, None, None, None,
+        None,
+    )?
+    .add_attribute("withdrawn", withdrawn_coin.to_string()))
+}
+
+fn split_exact(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    payouts: Vec<(String, Uint128)>,
+    on_invalid: InvalidPolicy,
+) -> Result<Response, ContractError> {
+    check_recipient_count(deps.storage, payouts.len())?;
+
+    // validate the addresses, setting aside the payouts to refund
+    let mut valid_payouts = vec![];
+    let mut refund = Uint128::zero();
+    for (address, amount) in payouts {
+        match normalize_addr(deps.api, &Addr::unchecked(address)) {
+            Ok(address) => valid_payouts.push((address, amount)),
+            Err(_) if on_invalid == InvalidPolicy::RefundSender => {
+                refund = refund.checked_add(amount).map_err(StdError::overflow)?;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+    let payouts = valid_payouts;
+    let recipients = payouts
+        .iter()
+        .map(|(address, _)| address)
+        .collect::<Vec<_>>();
+    let config = prepare_split(deps.as_ref(), &env, &info, &recipients)?;
+    let allowed_denoms = ALLOWED_DENOMS.load(deps.storage)?;
+    let sent_coin = validate_and_extract_coin(&info.funds, &allowed_denoms)?;
+
+    // check if the payouts and the fee cover exactly the amount sent
+    let config = denom_config(deps.storage, &config, &info.sender, &sent_coin.denom)?;
+    let min_fee = MIN_FEE.load(deps.storage)?;
+    let fee = fee_with_min(sent_coin.amount, &config, min_fee);
+    let mut total = fee.checked_add(refund).map_err(StdError::overflow)?;
+    for (_, amount) in &payouts {
+        total = total.checked_add(*amount).map_err(StdError::overflow)?;
+    }
+    if total != sent_coin.amount {
+        return Err(ContractError::AmountMismatch {
+            expected: sent_coin.amount,
+            got: total,
+        });
+    }
+
+    let total_fee = add_fee(deps.storage, &sent_coin.denom, fee)?;
+    for (address, amount) in &payouts {
+        if !amount.is_zero() {
+            credit_split(deps.storage, address, &sent_coin.denom, *amount)?;
+        }
+    }
+    if !refund.is_zero() {
+        credit(deps.storage, &info.sender, &sent_coin.denom, refund)?;
+    }
+
+    audit(deps.storage, &env, "split_exact", &info.sender, &info.funds)?;
+    let recipients = payouts.into_iter().map(|(address, _)| address).collect();
+    let fees = vec![coin(fee.u128(), &sent_coin.denom)];
+    record_split(
+        deps.storage,
+        &env,
+        &info.sender,
+        recipients,
+        info.funds,
+        fees,
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("method", "split_exact")
+        .add_attribute(
+            "refunded",
+            coin(refund.u128(), &sent_coin.denom).to_string(),
+        )
+        .add_attribute(
+            "total_fee",
+            coin(total_fee.u128(), sent_coin.denom).to_string(),
         ))
+}
+
+fn split_tip(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    tip_recipient: Addr,
+    tip_bps: u16,
+) -> Result<Response, ContractError> {
+    let tip_recipient = normalize_addr(deps.api, &tip_recipient)?;
+    let recipients = [&info.sender, &tip_recipient];
+    let config = prepare_split(deps.as_ref(), &env, &info, &recipients)?;
+    if tip_bps > 10_000 {
+        return Err(ContractError::InvalidTipBps {});
+    }
+
+    let min_fee = MIN_FEE.load(deps.storage)?;
+    let mut fees = vec![];
+    let mut total_fees = vec![];
+    for sent_coin in &info.funds {
+        // the sender keeps what is left after the fee and the tip
+        let coin_config = denom_config(deps.storage, &config, &info.sender, &sent_coin.denom)?;
+        let fee = fee_with_min(sent_coin.amount, &coin_config, min_fee);
+        fees.push(coin(fee.u128(), &sent_coin.denom));
+        let total_fee = add_fee(deps.storage, &sent_coin.denom, fee)?;
+        total_fees.push(coin(total_fee.u128(), &sent_coin.denom).to_string());
+
+        let tip = (sent_coin.amount - fee).multiply_ratio(tip_bps, 10_000u128);
+        let kept = sent_coin.amount - fee - tip;
+        for (address, amount) in [(&tip_recipient, tip), (&info.sender, kept)] {
+            if !amount.is_zero() {
+                credit_split(deps.storage, address, &sent_coin.denom, amount)?;
+            }
+        }
     }
+
+    audit(deps.storage, &env, "split_tip", &info.sender, &info.funds)?;
+    let recipients = vec![tip_recipient.clone()];
+    record_split(
+        deps.storage,
+        &env,
+        &info.sender,
+        recipients,
+        info.funds,
+        fees,
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("method", "split_tip")
+        .add_attribute("tip_recipient", tip_recipient)
+        .add_attribute("total_fee", total_fees.join(",")))
 }
 
-// this is a helper to move the tokens, so the business logic is easy to read
-fn send_tokens(to_address: Addr, amount: Vec<Coin>, action: &str) -> Response {
-    Response::new()
-        .add_message(BankMsg::Send {
-            to_address: to_address.clone().into(),
-            amount,
-        })
-        .add_attribute("action", action)
-        .add_attribute("to", to_address)
+fn split_by_alias(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient1: (Option<Addr>, Option<String>),
+    recipient2: (Option<Addr>, Option<String>),
+) -> Result<Response, ContractError> {
+    let recipient1 = resolve_recipient(deps.as_ref(), recipient1.0, recipient1.1)?;
+    let recipient2 = resolve_recipient(deps.as_ref(), recipient2.0, recipient2.1)?;
+    split(
+        deps, env, info, recipient1, recipient2, None, None, None, None, false, None, None, None,
+        None,
+    )
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::OwnerQuery {} => to_binary(&query_state(deps)?),
-        QueryMsg::WithdrawableAmount { address } => to_binary(&withdrawable_amount(deps, address)?),
+// returns the recipient given either by address or by registered alias
+fn resolve_recipient(
+    deps: Deps,
+    address: Option<Addr>,
+    alias: Option<String>,
+) -> Result<Addr, ContractError> {
+    match (address, alias) {
+        (Some(address), None) => Ok(address),
+        (None, Some(alias)) => ALIASES
+            .may_load(deps.storage, alias.clone())?
+            .ok_or(ContractError::UnknownAlias { alias }),
+        _ => Err(ContractError::InvalidRecipient {}),
     }
 }
 
-fn query_state(deps: Deps) -> StdResult<State> {
-    // returns the state
-    STATE.load(deps.storage)
+fn split_keep_half(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: Addr,
+) -> Result<Response, ContractError> {
+    // the sender is the second recipient of a regular split
+    let sender = info.sender.clone();
+    split(
+        deps, env, info, recipient, sender, None, None, None, None, false, None, None, None, None,
+    )
 }
 
-// returns the withdrawable amount for an address
-fn withdrawable_amount(deps: Deps, address: Addr) -> StdResult<u128> {
-    let amount = AMOUNTS.may_load(deps.storage, address)?;
-    if let Some(amount) = amount {
-        return Ok(amount);
-    } else {
-        return Ok(0);
+fn donate(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let allowed_denoms = ALLOWED_DENOMS.load(deps.storage)?;
+    let donation = validate_and_extract_coin(&info.funds, &allowed_denoms)?;
+    let total_fee = add_fee(deps.storage, &donation.denom, donation.amount)?;
+    audit(
+        deps.storage,
+        &env,
+        "donate",
+        &info.sender,
+        std::slice::from_ref(&donation),
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("method", "donate")
+        .add_attribute("donation", donation.to_string())
+        .add_attribute(
+            "total_fee",
+            coin(total_fee.u128(), donation.denom).to_string(),
+        ))
+}
+
+// rejects funds attached to a message that does not expect any
+fn nonpayable(info: &MessageInfo) -> Result<(), ContractError> {
+    if !info.funds.is_empty() {
+        return Err(ContractError::UnexpectedFunds {});
     }
+    Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::msg::OwnerResponse;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{coins, from_binary, CosmosMsg};
+// checks shared by every kind of split, returns the config to split with
+fn prepare_split(
+    deps: Deps,
+    env: &Env,
+    info: &MessageInfo,
+    recipients: &[&Addr],
+) -> Result<Config, ContractError> {
+    if FINALIZED.load(deps.storage)? {
+        return Err(ContractError::Finalized {});
+    }
+    if PAUSED.load(deps.storage)? {
+        return Err(ContractError::Paused {});
+    }
+    if info.funds.is_empty() {
+        return Err(ContractError::WrongCoinSent {});
+    }
+    if let Some(allowlist) = SENDER_ALLOWLIST.load(deps.storage)? {
+        if !allowlist.contains(&info.sender) {
+            return Err(ContractError::SenderNotAllowed {});
+        }
+    }
 
-    // checks if initialization was successful
-    #[test]
-    fn proper_initialization() {
-        let mut deps = mock_dependencies();
+    // the contract cannot withdraw funds credited to itself
+    if recipients
+        .iter()
+        .any(|recipient| **recipient == env.contract.address)
+    {
+        return Err(ContractError::RecipientIsContract {});
+    }
+    for recipient in recipients {
+        check_not_blocked(deps.storage, recipient)?;
+    }
 
-        let msg = InstantiateMsg {};
-        let info = mock_info("creator", &coins(0, "usei"));
+    // the sender may have its own fee rate
+    let mut config = CONFIG.load(deps.storage)?;
+    if let Some(fee_bps) = FEE_OVERRIDES.may_load(deps.storage, info.sender.clone())? {
+        config.fee_bps = fee_bps;
+    }
 
-        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
-        assert_eq!(0, res.messages.len());
+    let allowed_denoms = ALLOWED_DENOMS.load(deps.storage)?;
+    let max_deposit = MAX_DEPOSIT.load(deps.storage)?;
+    for sent_coin in &info.funds {
+        validate_denom(&sent_coin.denom, &allowed_denoms)?;
+        if sent_coin.amount < config.min_amount {
+            return Err(ContractError::AmountBelowMinimum {
+                min: config.min_amount,
+            });
+        }
+        if let Some(max) = max_deposit {
+            if sent_coin.amount > max {
+                return Err(ContractError::DepositTooLarge { max });
+            }
+        }
+    }
+    Ok(config)
+}
+
+// returns the config to split a coin of a denom with, the fee rate of the denom
+// replaces the configured one unless the sender has its own rate
+fn denom_config(
+    storage: &dyn Storage,
+    config: &Config,
+    sender: &Addr,
+    denom: &str,
+) -> StdResult<Config> {
+    let mut config = config.clone();
+    if !FEE_OVERRIDES.has(storage, sender.clone()) {
+        if let Some(fee_bps) = DENOM_FEE_BPS.may_load(storage, denom.to_string())? {
+            config.fee_bps = fee_bps;
+        }
+    }
+    Ok(config)
+}
+
+// computes the split of an amount made of a split amount plus its fee,
+// rejecting an amount no split amount adds up to with its fee
+fn compute_split_fee_on_top(
+    amount: Uint128,
+    config: &Config,
+    min_fee: Uint128,
+) -> Result<SplitBreakdown, ContractError> {
+    // the split amount plus its fee only grows with the split amount,
+    // so search for the largest split amount that fits
+    let total = |split_amount: Uint128| {
+        split_amount.saturating_add(compute_fee(split_amount, config).max(min_fee))
+    };
+    let (mut low, mut high) = (Uint128::zero(), amount);
+    while low < high {
+        let mid = low + (high - low + Uint128::new(1)) / Uint128::new(2);
+        if total(mid) <= amount {
+            low = mid;
+        } else {
+            high = mid - Uint128::new(1);
+        }
+    }
+    if total(low) != amount {
+        return Err(ContractError::InvalidFeeOnTop {
+            closest: total(low),
+        });
+    }
+    Ok(breakdown_after_fee(amount.u128(), (amount - low).u128(), 2))
+}
+
+// moves the rounding remainder of a split out of its fee into the dust if the dust
+// is tracked, so that fee + recipient1 + recipient2 + dust == sent amount.
+// Returns the fee left to collect
+fn take_dust(
+    storage: &mut dyn Storage,
+    denom: &str,
+    shares: &SplitBreakdown,
+) -> StdResult<Uint128> {
+    let track_dust = TRACK_DUST.load(storage)?;
+    if track_dust && shares.remainder != 0 {
+        DUST.update(storage, denom.to_string(), |dust| -> StdResult<Uint128> {
+            Ok(dust.unwrap_or_default() + Uint128::new(shares.remainder))
+        })?;
+    }
+    Ok(collected_fee(shares, track_dust))
+}
+
+// returns the fee a split collects, the rounding remainder is swept into the fee
+// unless it is tracked as dust
+fn collected_fee(shares: &SplitBreakdown, track_dust: bool) -> Uint128 {
+    if track_dust {
+        return Uint128::new(shares.fee);
+    }
+    Uint128::new(shares.fee + shares.remainder)
+}
+
+// rejects a split that leaves the recipients nothing
+fn check_split(shares: SplitBreakdown) -> Result<SplitBreakdown, ContractError> {
+    if shares.amount_each == 0 {
+        return Err(ContractError::AmountTooSmall {});
+    }
+    Ok(shares)
+}
+
+// returns the fee on an amount raised to the minimum fee, but never more than the amount
+fn fee_with_min(amount: Uint128, config: &Config, min_fee: Uint128) -> Uint128 {
+    compute_fee(amount, config).max(min_fee).min(amount)
+}
+
+// validates an address and returns its normalized form, so that differently
+// cased forms of one address share the same storage keys
+fn normalize_addr(api: &dyn Api, address: &Addr) -> StdResult<Addr> {
+    let normalized = api.addr_humanize(&api.addr_canonicalize(address.as_str())?)?;
+    api.addr_validate(normalized.as_str())
+}
+
+// rejects a withdrawal while the owner has frozen them
+fn check_withdrawals_open(storage: &dyn Storage) -> Result<(), ContractError> {
+    if WITHDRAWALS_FROZEN.load(storage)? {
+        return Err(ContractError::WithdrawalsFrozen {});
+    }
+    Ok(())
+}
+
+// rejects an address the owner has blocked
+fn check_not_blocked(storage: &dyn Storage, address: &Addr) -> Result<(), ContractError> {
+    if BLOCKED.has(storage, address.clone()) {
+        return Err(ContractError::RecipientBlocked {});
+    }
+    Ok(())
+}
+
+// adds the amount to the fee collected in a denom and returns the new total,
+// zero fees are not written
+fn add_fee(storage: &mut dyn Storage, denom: &str, fee: Uint128) -> Result<Uint128, ContractError> {
+    let total_fee = FEE_BY_DENOM
+        .may_load(storage, denom.to_string())?
+        .unwrap_or_default();
+    if fee.is_zero() {
+        return Ok(total_fee);
+    }
+    let total_fee = total_fee
+        .checked_add(fee)
+        .map_err(|_| ContractError::FeeOverflow {})?;
+    FEE_BY_DENOM.save(storage, denom.to_string(), &total_fee)?;
+    Ok(total_fee)
+}
+
+// returns the part of a vesting still locked at the current block time
+fn locked_amount(vesting: &Vesting, env: &Env) -> Uint128 {
+    let now = env.block.time.seconds();
+    if now < vesting.start + vesting.cliff {
+        return vesting.amount;
+    }
+    let elapsed = now - vesting.start;
+    if elapsed >= vesting.duration {
+        return Uint128::zero();
+    }
+    vesting.amount - vesting.amount.multiply_ratio(elapsed, vesting.duration)
+}
+
+// returns the part of a balance that is neither locked nor still vesting
+fn unlocked_amount(
+    storage: &dyn Storage,
+    env: &Env,
+    key: (Addr, String),
+    balance: Uint128,
+) -> StdResult<Uint128> {
+    let (locked, _) = active_locks(storage, env, key.clone())?;
+    let vesting = match VESTING.may_load(storage, key)? {
+        Some(vesting) => locked_amount(&vesting, env),
+        None => Uint128::zero(),
+    };
+    Ok(balance.saturating_sub(locked + vesting))
+}
+
+// returns the amount of a balance still locked and the earliest height one of its locks lapses
+fn active_locks(
+    storage: &dyn Storage,
+    env: &Env,
+    key: (Addr, String),
+) -> StdResult<(Uint128, Option<u64>)> {
+    let locks = LOCKS.may_load(storage, key)?.unwrap_or_default();
+    let active = locks
+        .iter()
+        .filter(|lock| env.block.height < lock.until)
+        .collect::<Vec<_>>();
+    let locked = active
+        .iter()
+        .fold(Uint128::zero(), |total, lock| total + lock.amount);
+    Ok((locked, active.first().map(|lock| lock.until)))
+}
+
+// locks an amount just credited to a balance, dropping the locks that have lapsed
+fn add_lock(
+    storage: &mut dyn Storage,
+    env: &Env,
+    key: (Addr, String),
+    amount: Uint128,
+    until: u64,
+) -> Result<(), ContractError> {
+    let mut locks = LOCKS.may_load(storage, key.clone())?.unwrap_or_default();
+    locks.retain(|lock| env.block.height < lock.until);
+    if locks.len() >= MAX_PARTS {
+        return Err(ContractError::TooManyParts { max: MAX_PARTS });
+    }
+    let index = locks.partition_point(|lock| lock.until <= until);
+    locks.insert(index, Lock { amount, until });
+    Ok(LOCKS.save(storage, key, &locks)?)
+}
+
+// lowers the locked parts of a balance so that they add up to no more than the balance,
+// earliest lock height first
+fn cap_locks(storage: &mut dyn Storage, key: (Addr, String), balance: Uint128) -> StdResult<()> {
+    let mut locks = match LOCKS.may_load(storage, key.clone())? {
+        Some(locks) => locks,
+        None => return Ok(()),
+    };
+    let total = locks
+        .iter()
+        .fold(Uint128::zero(), |total, lock| total + lock.amount);
+    let mut excess = total.saturating_sub(balance);
+    for lock in locks.iter_mut() {
+        let taken = lock.amount.min(excess);
+        lock.amount -= taken;
+        excess -= taken;
+    }
+    locks.retain(|lock| !lock.amount.is_zero());
+    if locks.is_empty() {
+        LOCKS.remove(storage, key);
+        return Ok(());
+    }
+    LOCKS.save(storage, key, &locks)
+}
+
+// adds the amount to the balance of an address, initializing it if not present
+fn credit(
+    storage: &mut dyn Storage,
+    address: &Addr,
+    denom: &str,
+    amount: Uint128,
+) -> StdResult<()> {
+    if !has_balance(storage, address) {
+        RECIPIENT_COUNT.update(storage, |count| -> StdResult<u64> { Ok(count + 1) })?;
+    }
+    if !AMOUNTS.has(storage, (address.clone(), denom.to_string())) {
+        MAP_SIZE.update(storage, |size| -> StdResult<u64> { Ok(size + 1) })?;
+    }
+    AMOUNTS.update(
+        storage,
+        (address.clone(), denom.to_string()),
+        |balance| -> StdResult<Uint128> { Ok(balance.unwrap_or_default() + amount) },
+    )?;
+    TOTAL_LIABILITIES.update(storage, denom.to_string(), |total| -> StdResult<Uint128> {
+        Ok(total.unwrap_or_default() + amount)
+    })?;
+    Ok(())
+}
+
+// lowers the balance of an address in a denom, which has to be kept above zero
+fn set_balance(storage: &mut dyn Storage, key: (Addr, String), balance: Uint128) -> StdResult<()> {
+    let previous = AMOUNTS.load(storage, key.clone())?;
+    TOTAL_LIABILITIES.update(storage, key.1.clone(), |total| -> StdResult<Uint128> {
+        Ok(total.unwrap_or_default() - (previous - balance))
+    })?;
+    take_expiring(storage, key.clone(), previous - balance)?;
+    cap_locks(storage, key.clone(), balance)?;
+    AMOUNTS.save(storage, key, &balance)
+}
+
+// marks an amount just credited to a balance as expiring, keeping the parts ordered by expiry
+fn add_expiry(
+    storage: &mut dyn Storage,
+    key: (Addr, String),
+    amount: Uint128,
+    expires_at: Timestamp,
+) -> Result<(), ContractError> {
+    let mut expiries = EXPIRIES.may_load(storage, key.clone())?.unwrap_or_default();
+    if expiries.len() >= MAX_PARTS {
+        return Err(ContractError::TooManyParts { max: MAX_PARTS });
+    }
+    let index = expiries.partition_point(|expiry| expiry.expires_at <= expires_at);
+    expiries.insert(index, Expiry { amount, expires_at });
+    Ok(EXPIRIES.save(storage, key, &expiries)?)
+}
+
+// takes an amount out of the expiring parts of a balance, earliest expiry first
+fn take_expiring(storage: &mut dyn Storage, key: (Addr, String), amount: Uint128) -> StdResult<()> {
+    let mut expiries = match EXPIRIES.may_load(storage, key.clone())? {
+        Some(expiries) => expiries,
+        None => return Ok(()),
+    };
+    let mut left = amount;
+    for expiry in expiries.iter_mut() {
+        let taken = expiry.amount.min(left);
+        expiry.amount -= taken;
+        left -= taken;
+    }
+    expiries.retain(|expiry| !expiry.amount.is_zero());
+    if expiries.is_empty() {
+        EXPIRIES.remove(storage, key);
+        return Ok(());
+    }
+    EXPIRIES.save(storage, key, &expiries)
+}
+
+// returns the sum of the balances held in a denom
+fn liabilities(storage: &dyn Storage, denom: &str) -> StdResult<Uint128> {
+    let total = TOTAL_LIABILITIES.may_load(storage, denom.to_string())?;
+    Ok(total.unwrap_or_default())
+}
+
+// credits a share of a split, adding it to the lifetime total of the address
+fn credit_split(
+    storage: &mut dyn Storage,
+    address: &Addr,
+    denom: &str,
+    amount: Uint128,
+) -> StdResult<()> {
+    credit(storage, address, denom, amount)?;
+    TOTAL_EARNED.update(
+        storage,
+        (address.clone(), denom.to_string()),
+        |earned| -> StdResult<Uint128> { Ok(earned.unwrap_or_default().checked_add(amount)?) },
+    )?;
+    Ok(())
+}
+
+// removes the balance of an address in a denom, uncounting the address if it was the last one
+fn remove_balance(storage: &mut dyn Storage, key: (Addr, String)) -> StdResult<()> {
+    let address = key.0.clone();
+    EXPIRIES.remove(storage, key.clone());
+    LOCKS.remove(storage, key.clone());
+    if let Some(balance) = AMOUNTS.may_load(storage, key.clone())? {
+        MAP_SIZE.update(storage, |size| -> StdResult<u64> { Ok(size - 1) })?;
+        TOTAL_LIABILITIES.update(storage, key.1.clone(), |total| -> StdResult<Uint128> {
+            Ok(total.unwrap_or_default() - balance)
+        })?;
+    }
+    AMOUNTS.remove(storage, key);
+    if !has_balance(storage, &address) {
+        RECIPIENT_COUNT.update(storage, |count| -> StdResult<u64> { Ok(count - 1) })?;
+    }
+    Ok(())
+}
+
+// returns whether an address holds a balance in any denom
+fn has_balance(storage: &dyn Storage, address: &Addr) -> bool {
+    AMOUNTS
+        .prefix(address.clone())
+        .keys(storage, None, None, Order::Ascending)
+        .next()
+        .is_some()
+}
+
+fn withdraw_fees(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Option<Uint128>,
+    denom: Option<String>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    check_withdrawals_open(deps.storage)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+
+    let amount = if amount.is_none() && denom.is_none() {
+        // take the fee collected in every denom
+        take_fees(deps.storage)?
+    } else {
+        // or part or all of the fee collected in one denom, the default denom if not set
+        let denom = denom.unwrap_or_else(|| config.denom.clone());
+        let fee = FEE_BY_DENOM
+            .may_load(deps.storage, denom.clone())?
+            .unwrap_or_default();
+        let amount = amount.unwrap_or(fee);
+        if amount.is_zero() || fee.is_zero() {
+            return Err(ContractError::NoFees {});
+        }
+        if amount > fee {
+            return Err(ContractError::ExceededQuantity {});
+        }
+        if amount == fee {
+            FEE_BY_DENOM.remove(deps.storage, denom.clone());
+        } else {
+            FEE_BY_DENOM.save(deps.storage, denom.clone(), &(fee - amount))?;
+        }
+        vec![coin(amount.u128(), denom)]
+    };
+
+    // transfer the fees to the fee recipient, restoring them if the send fails
+    let event = action_event("withdraw_fees", &config.fee_recipient, &amount);
+    audit(deps.storage, &env, "withdraw_fees", &info.sender, &amount)?;
+    Ok(send_with_rollback(
+        deps.storage,
+        config.fee_recipient.clone(),
+        config.fee_recipient,
+        amount,
+        WITHDRAW_FEES_REPLY_ID,
+    )?
+    .add_event(event))
+}
+
+fn withdraw_dust(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    let amount = query_dust(deps.as_ref())?;
+    if amount.is_empty() {
+        return Err(ContractError::NoDust {});
+    }
+    for dust in &amount {
+        DUST.remove(deps.storage, dust.denom.clone());
+    }
+    audit(deps.storage, &env, "withdraw_dust", &info.sender, &amount)?;
+
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount,
+        })
+        .add_attribute("method", "withdraw_dust"))
+}
+
+fn reconcile_fees(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    // everything owed to recipients, including withdrawals queued for the next flush
+    let denom = CONFIG.load(deps.storage)?.denom;
+    let dust = DUST
+        .may_load(deps.storage, denom.clone())?
+        .unwrap_or_default();
+    let owed = liabilities(deps.storage, &denom)? + queued_payouts(deps.storage, &denom)? + dust;
+
+    // the surplus over the balances owed becomes the fee, unless the fee is already higher
+    let holdings = deps
+        .querier
+        .query_balance(&env.contract.address, denom.clone())?
+        .amount;
+    let fee = FEE_BY_DENOM
+        .may_load(deps.storage, denom.clone())?
+        .unwrap_or_default();
+    let adjustment = holdings.saturating_sub(owed).saturating_sub(fee);
+    let total_fee = add_fee(deps.storage, &denom, adjustment)?;
+    audit(
+        deps.storage,
+        &env,
+        "reconcile_fees",
+        &info.sender,
+        &[coin(adjustment.u128(), &denom)],
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("method", "reconcile_fees")
+        .add_attribute("adjustment", coin(adjustment.u128(), &denom).to_string())
+        .add_attribute("total_fee", coin(total_fee.u128(), denom).to_string()))
+}
+
+fn withdraw_fees_split(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipients: Vec<(Addr, u16)>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    check_withdrawals_open(deps.storage)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    // check if the shares cover the whole pot
+    let total: u32 = recipients.iter().map(|(_, bps)| u32::from(*bps)).sum();
+    if total != 10000 {
+        return Err(ContractError::InvalidShares {});
+    }
+    let recipients = recipients
+        .into_iter()
+        .map(|(recipient, bps)| Ok((normalize_addr(deps.api, &recipient)?, bps)))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    // every treasury takes its share, the last one also takes the rounding dust
+    let fees = take_fees(deps.storage)?;
+    audit(deps.storage, &env, "withdraw_fees", &info.sender, &fees)?;
+    let mut sends = vec![Uint128::zero(); fees.len()];
+    let mut res = Response::new().add_attribute("action", "withdraw_fees_split");
+    for (i, (recipient, bps)) in recipients.iter().enumerate() {
+        let mut amount = vec![];
+        for (fee, sent) in fees.iter().zip(sends.iter_mut()) {
+            let share = if i == recipients.len() - 1 {
+                fee.amount - *sent
+            } else {
+                fee.amount.multiply_ratio(*bps, 10000u128)
+            };
+            *sent += share;
+            if !share.is_zero() {
+                amount.push(coin(share.u128(), fee.denom.clone()));
+            }
+        }
+        if !amount.is_empty() {
+            res = res.add_message(BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount,
+            });
+        }
+    }
+
+    Ok(res)
+}
+
+// clears the fee collected in every denom and returns it
+fn take_fees(storage: &mut dyn Storage) -> Result<Vec<Coin>, ContractError> {
+    let fees = FEE_BY_DENOM
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut amount = vec![];
+    for (denom, fee) in fees {
+        FEE_BY_DENOM.remove(storage, denom.clone());
+        if !fee.is_zero() {
+            amount.push(coin(fee.u128(), denom));
+        }
+    }
+    if amount.is_empty() {
+        return Err(ContractError::NoFees {});
+    }
+    Ok(amount)
+}
+
+fn reclaim(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    check_withdrawals_open(deps.storage)?;
+
+    let window = RECLAIM_WINDOW
+        .load(deps.storage)?
+        .ok_or(ContractError::ReclaimDisabled {})?;
+    let deposit = DEPOSITS
+        .may_load(deps.storage, info.sender.clone())?
+        .ok_or(ContractError::NoDeposit {})?;
+
+    // check if the deposit is still within the window
+    if env.block.height > deposit.height + window {
+        return Err(ContractError::ReclaimWindowExpired {});
+    }
+
+    // take the deposit back from both recipients, the fee is not refunded
+    for recipient in [&deposit.recipient1, &deposit.recipient2] {
+        // once a recipient withdrew, what is left may have been sent by others
+        if let Some(last) = LAST_WITHDRAW_BLOCK.may_load(deps.storage, recipient.clone())? {
+            if last >= deposit.height {
+                return Err(ContractError::DepositWithdrawn {});
+            }
+        }
+        for share in &deposit.amount {
+            let key = (recipient.clone(), share.denom.clone());
+            let amount = AMOUNTS
+                .may_load(deps.storage, key.clone())?
+                .unwrap_or_default();
+            if amount < share.amount {
+                return Err(ContractError::DepositWithdrawn {});
+            }
+            // a reclaimed share no longer vests, the vesting of any other split stays
+            if deposit.vesting {
+                if let Some(vesting) = VESTING.may_load(deps.storage, key.clone())? {
+                    if vesting.start == deposit.time.seconds() {
+                        VESTING.remove(deps.storage, key.clone());
+                    }
+                }
+            }
+            if amount == share.amount {
+                remove_balance(deps.storage, key)?;
+            } else {
+                set_balance(deps.storage, key, amount - share.amount)?;
+            }
+        }
+    }
+    DEPOSITS.remove(deps.storage, info.sender.clone());
+
+    let refund: Vec<Coin> = deposit
+        .amount
+        .into_iter()
+        .map(|share| coin((share.amount * Uint128::new(2)).u128(), share.denom))
+        .collect();
+    audit(deps.storage, &env, "reclaim", &info.sender, &refund)?;
+    Ok(send_tokens(info.sender, refund, "reclaim"))
+}
+
+fn set_withdraw_limit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    limit: Option<Uint128>,
+    window: u64,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    WITHDRAW_LIMIT.save(deps.storage, &limit)?;
+    WITHDRAW_WINDOW.save(deps.storage, &window)?;
+    audit(deps.storage, &env, "set_withdraw_limit", &info.sender, &[])?;
+
+    Ok(Response::new().add_attribute("method", "set_withdraw_limit"))
+}
+
+fn set_withdraw_cooldown(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    blocks: u64,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    WITHDRAW_COOLDOWN.save(deps.storage, &blocks)?;
+    audit(
+        deps.storage,
+        &env,
+        "set_withdraw_cooldown",
+        &info.sender,
+        &[],
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_withdraw_cooldown")
+        .add_attribute("blocks", blocks.to_string()))
+}
+
+fn set_fee_override(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    sender: Addr,
+    fee_bps: Option<u16>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    match fee_bps {
+        Some(fee_bps) => {
+            if fee_bps > 10_000 {
+                return Err(ContractError::InvalidFeeBps {});
+            }
+            FEE_OVERRIDES.save(deps.storage, sender.clone(), &fee_bps)?;
+        }
+        None => FEE_OVERRIDES.remove(deps.storage, sender.clone()),
+    }
+    audit(deps.storage, &env, "set_fee_override", &info.sender, &[])?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_fee_override")
+        .add_attribute("sender", sender))
+}
+
+fn set_denom_fee(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    denom: String,
+    fee_bps: Option<u16>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    match fee_bps {
+        Some(fee_bps) => {
+            if fee_bps > 10_000 {
+                return Err(ContractError::InvalidFeeBps {});
+            }
+            validate_denom(&denom, &ALLOWED_DENOMS.load(deps.storage)?)?;
+            DENOM_FEE_BPS.save(deps.storage, denom.clone(), &fee_bps)?;
+        }
+        None => DENOM_FEE_BPS.remove(deps.storage, denom.clone()),
+    }
+    audit(deps.storage, &env, "set_denom_fee", &info.sender, &[])?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_denom_fee")
+        .add_attribute("denom", denom))
+}
+
+fn set_referrer_bps(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    referrer_bps: u16,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    if referrer_bps > 10_000 {
+        return Err(ContractError::InvalidReferrerBps {});
+    }
+    REFERRER_BPS.save(deps.storage, &referrer_bps)?;
+    audit(deps.storage, &env, "set_referrer_bps", &info.sender, &[])?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_referrer_bps")
+        .add_attribute("referrer_bps", referrer_bps.to_string()))
+}
+
+fn create_coupon(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    code: String,
+    fee_bps: u16,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    if fee_bps > 10_000 {
+        return Err(ContractError::InvalidFeeBps {});
+    }
+    COUPONS.save(deps.storage, code, &fee_bps)?;
+    audit(deps.storage, &env, "create_coupon", &info.sender, &[])?;
+
+    Ok(Response::new().add_attribute("method", "create_coupon"))
+}
+
+fn set_min_withdrawal(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    min: Uint128,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    MIN_WITHDRAWAL.save(deps.storage, &min)?;
+    audit(deps.storage, &env, "set_min_withdrawal", &info.sender, &[])?;
+
+    Ok(Response::new().add_attribute("method", "set_min_withdrawal"))
+}
+
+fn set_max_recipients(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    max: u32,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    if max == 0 || max > MAX_RECIPIENTS_CAP {
+        return Err(ContractError::InvalidMaxRecipients {
+            cap: MAX_RECIPIENTS_CAP,
+        });
+    }
+    MAX_RECIPIENTS.save(deps.storage, &max)?;
+    audit(deps.storage, &env, "set_max_recipients", &info.sender, &[])?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_max_recipients")
+        .add_attribute("max", max.to_string()))
+}
+
+// rejects a list of payouts or destinations longer than the owner allows
+fn check_recipient_count(storage: &dyn Storage, count: usize) -> Result<(), ContractError> {
+    let max = MAX_RECIPIENTS.load(storage)?;
+    if count > max as usize {
+        return Err(ContractError::TooManyRecipients { max });
+    }
+    Ok(())
+}
+
+fn set_max_deposit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    max: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    MAX_DEPOSIT.save(deps.storage, &max)?;
+    audit(deps.storage, &env, "set_max_deposit", &info.sender, &[])?;
+
+    Ok(Response::new().add_attribute("method", "set_max_deposit"))
+}
+
+fn set_fee_auto_forward(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    threshold: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    FEE_AUTO_FORWARD_THRESHOLD.save(deps.storage, &threshold)?;
+    audit(
+        deps.storage,
+        &env,
+        "set_fee_auto_forward",
+        &info.sender,
+        &[],
+    )?;
+
+    Ok(Response::new().add_attribute("method", "set_fee_auto_forward"))
+}
+
+fn set_paused(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    paused: bool,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    check_admin(deps.storage, &info.sender)?;
+    audit(deps.storage, &env, "set_paused", &info.sender, &[])?;
+
+    Ok(save_paused(deps.storage, paused)?)
+}
+
+fn set_withdrawals_frozen(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    frozen: bool,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    WITHDRAWALS_FROZEN.save(deps.storage, &frozen)?;
+    audit(
+        deps.storage,
+        &env,
+        "set_withdrawals_frozen",
+        &info.sender,
+        &[],
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_withdrawals_frozen")
+        .add_attribute("frozen", frozen.to_string()))
+}
+
+fn set_fee_rate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    fee_bps: u16,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    check_admin(deps.storage, &info.sender)?;
+
+    let res = save_fee_rate(deps.storage, &env, fee_bps)?;
+    audit(deps.storage, &env, "set_fee_rate", &info.sender, &[])?;
+    Ok(res)
+}
+
+// rejects a sender that is neither the owner nor an admin
+fn check_admin(storage: &dyn Storage, sender: &Addr) -> Result<(), ContractError> {
+    if *sender != STATE.load(storage)?.owner && !ADMINS.load(storage)?.contains(sender) {
+        return Err(ContractError::NotAdmin {});
+    }
+    Ok(())
+}
+
+fn add_admin(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    admin: Addr,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    let admin = deps.api.addr_validate(admin.as_str())?;
+    ADMINS.update(deps.storage, |mut admins| -> StdResult<_> {
+        if !admins.contains(&admin) {
+            admins.push(admin.clone());
+        }
+        Ok(admins)
+    })?;
+    audit(deps.storage, &env, "add_admin", &info.sender, &[])?;
+
+    Ok(Response::new()
+        .add_attribute("method", "add_admin")
+        .add_attribute("admin", admin))
+}
+
+fn remove_admin(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    admin: Addr,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    ADMINS.update(deps.storage, |mut admins| -> StdResult<_> {
+        admins.retain(|existing| *existing != admin);
+        Ok(admins)
+    })?;
+    audit(deps.storage, &env, "remove_admin", &info.sender, &[])?;
+
+    Ok(Response::new()
+        .add_attribute("method", "remove_admin")
+        .add_attribute("admin", admin))
+}
+
+// pauses or unpauses new splits, shared by the owner and governance
+fn save_paused(storage: &mut dyn Storage, paused: bool) -> StdResult<Response> {
+    PAUSED.save(storage, &paused)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_paused")
+        .add_attribute("paused", paused.to_string()))
+}
+
+// changes the fee rate, shared by the owner and governance
+fn save_fee_rate(
+    storage: &mut dyn Storage,
+    env: &Env,
+    fee_bps: u16,
+) -> Result<Response, ContractError> {
+    if fee_bps > 10_000 {
+        return Err(ContractError::InvalidFeeBps {});
+    }
+    CONFIG.update(storage, |mut config| -> StdResult<_> {
+        config.fee_bps = fee_bps;
+        Ok(config)
+    })?;
+    FEE_RATE_HISTORY.save(storage, env.block.height, &fee_bps)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_fee_rate")
+        .add_attribute("fee_bps", fee_bps.to_string()))
+}
+
+// recovers the whole native balance, recorded balances and fees are left untouched
+fn emergency_sweep(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    to: Addr,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    // sweeping is only allowed while the contract is paused
+    if !PAUSED.load(deps.storage)? {
+        return Err(ContractError::NotPaused {});
+    }
+
+    let denom = CONFIG.load(deps.storage)?.denom;
+    let balance = deps
+        .querier
+        .query_balance(env.contract.address.clone(), denom)?;
+    audit(
+        deps.storage,
+        &env,
+        "emergency_sweep",
+        &info.sender,
+        std::slice::from_ref(&balance),
+    )?;
+
+    Ok(send_tokens(to, vec![balance.clone()], "emergency_sweep")
+        .add_attribute("owner", info.sender)
+        .add_attribute("amount", balance.to_string()))
+}
+
+fn force_withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: Addr,
+    to: Addr,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+    let recipient = normalize_addr(deps.api, &recipient)?;
+    let to = normalize_addr(deps.api, &to)?;
+
+    // moving a recipient's balance is only allowed while the contract is paused
+    if !PAUSED.load(deps.storage)? {
+        return Err(ContractError::NotPaused {});
+    }
+
+    let balances = AMOUNTS
+        .prefix(recipient.clone())
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut amount = vec![];
+    for (denom, balance) in balances {
+        remove_balance(deps.storage, (recipient.clone(), denom.clone()))?;
+        VESTING.remove(deps.storage, (recipient.clone(), denom.clone()));
+        if !balance.is_zero() {
+            amount.push(coin(balance.u128(), denom));
+        }
+    }
+    if amount.is_empty() {
+        return Err(ContractError::NothingToWithdraw {});
+    }
+    audit(deps.storage, &env, "force_withdraw", &info.sender, &amount)?;
+
+    let amount_attr = amount
+        .iter()
+        .map(|coin| coin.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    Ok(send_tokens(to, amount, "force_withdraw")
+        .add_attribute("owner", info.sender)
+        .add_attribute("recipient", recipient)
+        .add_attribute("amount", amount_attr))
+}
+
+fn add_denom(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    denom: String,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    validate_denom_format(&denom)?;
+    ALLOWED_DENOMS.update(deps.storage, |mut denoms| -> StdResult<_> {
+        if !denoms.contains(&denom) {
+            denoms.push(denom.clone());
+        }
+        Ok(denoms)
+    })?;
+    audit(deps.storage, &env, "add_denom", &info.sender, &[])?;
+
+    Ok(Response::new()
+        .add_attribute("method", "add_denom")
+        .add_attribute("denom", denom))
+}
+
+fn remove_denom(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    denom: String,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    ALLOWED_DENOMS.update(deps.storage, |mut denoms| -> StdResult<_> {
+        denoms.retain(|allowed| *allowed != denom);
+        Ok(denoms)
+    })?;
+    audit(deps.storage, &env, "remove_denom", &info.sender, &[])?;
+
+    Ok(Response::new()
+        .add_attribute("method", "remove_denom")
+        .add_attribute("denom", denom))
+}
+
+fn add_sender(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    sender: Addr,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    let sender = deps.api.addr_validate(sender.as_str())?;
+    SENDER_ALLOWLIST.update(deps.storage, |allowlist| -> StdResult<_> {
+        let mut allowlist = allowlist.unwrap_or_default();
+        if !allowlist.contains(&sender) {
+            allowlist.push(sender.clone());
+        }
+        Ok(Some(allowlist))
+    })?;
+    audit(deps.storage, &env, "add_sender", &info.sender, &[])?;
+
+    Ok(Response::new()
+        .add_attribute("method", "add_sender")
+        .add_attribute("sender", sender))
+}
+
+fn remove_sender(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    sender: Addr,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    SENDER_ALLOWLIST.update(deps.storage, |allowlist| -> StdResult<_> {
+        let mut allowlist = allowlist.unwrap_or_default();
+        allowlist.retain(|allowed| *allowed != sender);
+        Ok(Some(allowlist))
+    })?;
+    audit(deps.storage, &env, "remove_sender", &info.sender, &[])?;
+
+    Ok(Response::new()
+        .add_attribute("method", "remove_sender")
+        .add_attribute("sender", sender))
+}
+
+fn set_blocked(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: Addr,
+    blocked: bool,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+    let address = normalize_addr(deps.api, &address)?;
+
+    if blocked {
+        BLOCKED.save(deps.storage, address.clone(), &())?;
+    } else {
+        BLOCKED.remove(deps.storage, address.clone());
+    }
+    audit(deps.storage, &env, "set_blocked", &info.sender, &[])?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_blocked")
+        .add_attribute("address", address)
+        .add_attribute("blocked", blocked.to_string()))
+}
+
+fn import_balances(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    entries: Vec<(Addr, Uint128)>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    // balances can only move while no splits are coming in
+    if !PAUSED.load(deps.storage)? {
+        return Err(ContractError::NotPaused {});
+    }
+
+    // every address is imported once, so replaying a batch is rejected
+    let denom = CONFIG.load(deps.storage)?.denom;
+    let mut total = Uint128::zero();
+    for (address, amount) in &entries {
+        let address = deps.api.addr_validate(address.as_str())?;
+        if IMPORTED.has(deps.storage, address.clone()) {
+            return Err(ContractError::AlreadyImported {
+                address: address.into(),
+            });
+        }
+        IMPORTED.save(deps.storage, address.clone(), &())?;
+        if !amount.is_zero() {
+            credit(deps.storage, &address, &denom, *amount)?;
+        }
+        total = total.checked_add(*amount).map_err(StdError::overflow)?;
+    }
+    audit(
+        deps.storage,
+        &env,
+        "import_balances",
+        &info.sender,
+        &[coin(total.u128(), denom)],
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("method", "import_balances")
+        .add_attribute("count", entries.len().to_string()))
+}
+
+fn sweep_expired(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: Addr,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+    let recipient = normalize_addr(deps.api, &recipient)?;
+
+    let balances = AMOUNTS
+        .prefix(recipient.clone())
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut swept = vec![];
+    for (denom, balance) in balances {
+        // only the parts past their expiry are swept
+        let key = (recipient.clone(), denom.clone());
+        let expired = EXPIRIES
+            .may_load(deps.storage, key.clone())?
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|expiry| env.block.time > expiry.expires_at)
+            .fold(Uint128::zero(), |total, expiry| total + expiry.amount);
+        if expired.is_zero() {
+            continue;
+        }
+
+        // the expired parts come first, so lowering the balance takes exactly them
+        if expired == balance {
+            VESTING.remove(deps.storage, key.clone());
+            remove_balance(deps.storage, key)?;
+        } else {
+            set_balance(deps.storage, key, balance - expired)?;
+        }
+        add_fee(deps.storage, &denom, expired)?;
+        swept.push(coin(expired.u128(), denom));
+    }
+    if swept.is_empty() {
+        return Err(ContractError::NotExpired {});
+    }
+    audit(deps.storage, &env, "sweep_expired", &info.sender, &swept)?;
+    let swept: Vec<String> = swept.iter().map(Coin::to_string).collect();
+
+    Ok(Response::new()
+        .add_attribute("method", "sweep_expired")
+        .add_attribute("recipient", recipient)
+        .add_attribute("swept", swept.join(",")))
+}
+
+fn finalize(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    // finalizing is one-way
+    if FINALIZED.load(deps.storage)? {
+        return Err(ContractError::Finalized {});
+    }
+    FINALIZED.save(deps.storage, &true)?;
+    audit(deps.storage, &env, "finalize", &info.sender, &[])?;
+
+    Ok(Response::new().add_attribute("method", "finalize"))
+}
+
+fn propose_owner(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    owner: Addr,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    PENDING_OWNER.save(deps.storage, &owner)?;
+    audit(deps.storage, &env, "propose_owner", &info.sender, &[])?;
+
+    Ok(Response::new()
+        .add_attribute("method", "propose_owner")
+        .add_attribute("pending_owner", owner))
+}
+
+fn accept_ownership(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the proposed owner
+    if PENDING_OWNER.may_load(deps.storage)? != Some(info.sender.clone()) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    PENDING_OWNER.remove(deps.storage);
+    STATE.save(
+        deps.storage,
+        &State {
+            owner: info.sender.clone(),
+        },
+    )?;
+    audit(deps.storage, &env, "accept_ownership", &info.sender, &[])?;
+
+    Ok(Response::new()
+        .add_attribute("method", "accept_ownership")
+        .add_attribute("owner", info.sender))
+}
+
+fn cancel_ownership_proposal(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    PENDING_OWNER.remove(deps.storage);
+    audit(
+        deps.storage,
+        &env,
+        "cancel_ownership_proposal",
+        &info.sender,
+        &[],
+    )?;
+
+    Ok(Response::new().add_attribute("method", "cancel_ownership_proposal"))
+}
+
+fn prune_zero_balances(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    limit: u32,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // check if the sender is the owner
+    if info.sender != STATE.load(deps.storage)?.owner {
+        return Err(ContractError::NotOwner {});
+    }
+
+    let zero_balances = AMOUNTS
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| item.as_ref().map_or(true, |(_, amount)| amount.is_zero()))
+        .take(limit as usize)
+        .map(|item| item.map(|(key, _)| key))
+        .collect::<StdResult<Vec<_>>>()?;
+    let pruned = zero_balances.len() as u32;
+    for key in zero_balances {
+        remove_balance(deps.storage, key)?;
+    }
+    audit(deps.storage, &env, "prune_zero_balances", &info.sender, &[])?;
+
+    Ok(Response::new()
+        .add_attribute("method", "prune_zero_balances")
+        .add_attribute("pruned", pruned.to_string())
+        .set_data(to_binary(&pruned)?))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    quantity: Option<Uint128>,
+    denom: Option<String>,
+    percent: Option<u8>,
+    from: Option<Addr>,
+    to: Option<Addr>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // a delegate can withdraw the balance of the recipient that set it
+    let from = from
+        .map(|from| normalize_addr(deps.api, &from))
+        .transpose()?;
+    let to = to.map(|to| normalize_addr(deps.api, &to)).transpose()?;
+    let recipient = match from {
+        Some(from) if from != info.sender => {
+            if DELEGATES.may_load(deps.storage, from.clone())? != Some(info.sender) {
+                return Err(ContractError::Unauthorized {});
+            }
+            from
+        }
+        _ => info.sender,
+    };
+    let to = to.unwrap_or_else(|| recipient.clone());
+    check_not_blocked(deps.storage, &to)?;
+
+    let withdrawal =
+        validate_withdrawal(deps.as_ref(), &env, &recipient, quantity, denom, percent)?;
+    let withdrawn_coin = withdrawal.amount[0].clone();
+    let key = (recipient.clone(), withdrawn_coin.denom.clone());
+    check_withdraw_limit(deps.storage, &env, &key, withdrawn_coin.amount)?;
+
+    // update the store and send the tokens, keeping any balance still vesting
+    if quantity.is_some() || percent.is_some() || !withdrawal.remaining.is_zero() {
+        set_balance(deps.storage, key, withdrawal.remaining)?;
+    } else {
+        remove_balance(deps.storage, key)?;
+    }
+
+    let data = WithdrawResponse {
+        withdrawn: withdrawn_coin.clone(),
+        remaining: withdrawal.remaining,
+    };
+    Ok(
+        send_withdrawal(deps.storage, &env, recipient, to, withdrawal.amount)?
+            .add_attribute("amount", withdrawn_coin.amount)
+            .add_attribute("balance", withdrawal.remaining)
+            .set_data(to_binary(&data)?),
+    )
+}
+
+fn withdraw_split(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    destinations: Vec<(Addr, u16)>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    check_recipient_count(deps.storage, destinations.len())?;
+
+    // check if the shares cover the whole balance
+    let total: u32 = destinations.iter().map(|(_, bps)| u32::from(*bps)).sum();
+    if total != 10000 {
+        return Err(ContractError::InvalidShares {});
+    }
+    let destinations = destinations
+        .into_iter()
+        .map(|(destination, bps)| Ok((normalize_addr(deps.api, &destination)?, bps)))
+        .collect::<StdResult<Vec<_>>>()?;
+    for (destination, _) in &destinations {
+        check_not_blocked(deps.storage, destination)?;
+    }
+
+    // take the balance, keeping any part still vesting
+    let withdrawal = validate_withdrawal(deps.as_ref(), &env, &info.sender, None, None, None)?;
+    let withdrawn_coin = withdrawal.amount[0].clone();
+    let key = (info.sender.clone(), withdrawn_coin.denom.clone());
+    check_withdraw_limit(deps.storage, &env, &key, withdrawn_coin.amount)?;
+    if withdrawal.remaining.is_zero() {
+        remove_balance(deps.storage, key)?;
+    } else {
+        set_balance(deps.storage, key, withdrawal.remaining)?;
+    }
+    let event = action_event("withdraw", &info.sender, &withdrawal.amount);
+    audit(
+        deps.storage,
+        &env,
+        "withdraw",
+        &info.sender,
+        &withdrawal.amount,
+    )?;
+    LAST_WITHDRAW_BLOCK.save(deps.storage, info.sender.clone(), &env.block.height)?;
+
+    // every destination takes its share, the last one also takes the rounding dust
+    let batch_payouts = BATCH_PAYOUTS.load(deps.storage)?;
+    let mut res = Response::new()
+        .add_event(event)
+        .add_attribute("method", "withdraw_split");
+    let mut sent = Uint128::zero();
+    let last = destinations.len() - 1;
+    for (i, (destination, bps)) in destinations.into_iter().enumerate() {
+        let share = if i == last {
+            withdrawn_coin.amount - sent
+        } else {
+            withdrawn_coin.amount.multiply_ratio(bps, 10000u128)
+        };
+        sent += share;
+        if share.is_zero() {
+            continue;
+        }
+        let amount = vec![coin(share.u128(), &withdrawn_coin.denom)];
+        if batch_payouts {
+            let payout = PendingPayout {
+                recipient: info.sender.clone(),
+                to: destination,
+                amount,
+            };
+            queue_payout(deps.storage, &payout)?;
+        } else {
+            res = res.add_message(BankMsg::Send {
+                to_address: destination.into(),
+                amount,
+            });
+        }
+    }
+
+    Ok(res)
+}
+
+// checks everything a withdrawal is held to without updating the state,
+// shared by the withdraw handler and the simulation query
+fn validate_withdrawal(
+    deps: Deps,
+    env: &Env,
+    recipient: &Addr,
+    quantity: Option<Uint128>,
+    denom: Option<String>,
+    percent: Option<u8>,
+) -> Result<PreviewWithdrawResponse, ContractError> {
+    check_withdrawals_open(deps.storage)?;
+    check_not_blocked(deps.storage, recipient)?;
+    check_cooldown(deps.storage, env, recipient)?;
+
+    let withdrawal = compute_withdrawal(deps, env, recipient, quantity, denom, percent)?;
+    let withdrawn_coin = &withdrawal.amount[0];
+
+    // a bank send of zero coins is invalid
+    if withdrawn_coin.amount.is_zero() {
+        return Err(ContractError::NothingToWithdraw {});
+    }
+
+    // only partial withdrawals are held to the minimum
+    let min = MIN_WITHDRAWAL.load(deps.storage)?;
+    if (quantity.is_some() || percent.is_some()) && withdrawn_coin.amount < min {
+        return Err(ContractError::WithdrawalTooSmall { min });
+    }
+
+    let key = (recipient.clone(), withdrawn_coin.denom.clone());
+    window_withdrawal(deps.storage, env, &key, withdrawn_coin.amount)?;
+    Ok(withdrawal)
+}
+
+fn register_alias(
+    deps: DepsMut,
+    info: MessageInfo,
+    alias: String,
+    address: Option<Addr>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // only the owner can register an alias for another address or take over one
+    let is_owner = info.sender == STATE.load(deps.storage)?.owner;
+    let address = match address {
+        Some(address) if address != info.sender => {
+            if !is_owner {
+                return Err(ContractError::NotOwner {});
+            }
+            normalize_addr(deps.api, &address)?
+        }
+        _ => info.sender,
+    };
+    if let Some(registered) = ALIASES.may_load(deps.storage, alias.clone())? {
+        if registered != address && !is_owner {
+            return Err(ContractError::AliasTaken { alias });
+        }
+    }
+    ALIASES.save(deps.storage, alias.clone(), &address)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "register_alias")
+        .add_attribute("alias", alias)
+        .add_attribute("address", address))
+}
+
+fn register_callback(
+    deps: DepsMut,
+    info: MessageInfo,
+    contract: Option<Addr>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let mut res = Response::new().add_attribute("method", "register_callback");
+    match contract {
+        Some(contract) => {
+            let contract = deps.api.addr_validate(contract.as_str())?;
+            CALLBACKS.save(deps.storage, info.sender, &contract)?;
+            res = res.add_attribute("contract", contract);
+        }
+        None => CALLBACKS.remove(deps.storage, info.sender),
+    }
+    Ok(res)
+}
+
+fn set_withdraw_delegate(
+    deps: DepsMut,
+    info: MessageInfo,
+    delegate: Option<Addr>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let mut res = Response::new().add_attribute("method", "set_withdraw_delegate");
+    match delegate {
+        Some(delegate) => {
+            let delegate = deps.api.addr_validate(delegate.as_str())?;
+            DELEGATES.save(deps.storage, info.sender, &delegate)?;
+            res = res.add_attribute("delegate", delegate);
+        }
+        None => DELEGATES.remove(deps.storage, info.sender),
+    }
+    Ok(res)
+}
+
+fn withdraw_all(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let balances = AMOUNTS
+        .prefix(info.sender.clone())
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    withdraw_balances(deps, &env, info.sender, balances)
+}
+
+fn withdraw_denoms(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    mut denoms: Vec<String>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // every listed denom must be held, duplicates are withdrawn once
+    denoms.sort();
+    denoms.dedup();
+    let mut balances = vec![];
+    for denom in denoms {
+        let balance = AMOUNTS
+            .may_load(deps.storage, (info.sender.clone(), denom.clone()))?
+            .ok_or_else(|| ContractError::DenomNotHeld {
+                denom: denom.clone(),
+            })?;
+        balances.push((denom, balance));
+    }
+
+    withdraw_balances(deps, &env, info.sender, balances)
+}
+
+// removes every unlocked balance and sends the non-zero ones in a single message
+fn withdraw_balances(
+    deps: DepsMut,
+    env: &Env,
+    recipient: Addr,
+    balances: Vec<(String, Uint128)>,
+) -> Result<Response, ContractError> {
+    check_withdrawals_open(deps.storage)?;
+    check_not_blocked(deps.storage, &recipient)?;
+    check_cooldown(deps.storage, env, &recipient)?;
+
+    let mut amount = vec![];
+    for (denom, balance) in balances {
+        let key = (recipient.clone(), denom.clone());
+        let unlocked = unlocked_amount(deps.storage, env, key.clone(), balance)?;
+        check_withdraw_limit(deps.storage, env, &key, unlocked)?;
+        if unlocked == balance {
+            remove_balance(deps.storage, key)?;
+        } else {
+            set_balance(deps.storage, key, balance - unlocked)?;
+        }
+        if !unlocked.is_zero() {
+            amount.push(coin(unlocked.u128(), denom));
+        }
+    }
+    if amount.is_empty() {
+        return Err(ContractError::NothingToWithdraw {});
+    }
+
+    Ok(send_withdrawal(
+        deps.storage,
+        env,
+        recipient.clone(),
+        recipient,
+        amount,
+    )?)
+}
+
+// rejects a withdrawal before the cooldown since the recipient's latest one has passed
+fn check_cooldown(storage: &dyn Storage, env: &Env, recipient: &Addr) -> Result<(), ContractError> {
+    let cooldown = WITHDRAW_COOLDOWN.load(storage)?;
+    if let Some(last) = LAST_WITHDRAW_BLOCK.may_load(storage, recipient.clone())? {
+        let retry_at = last + cooldown;
+        if env.block.height < retry_at {
+            return Err(ContractError::CooldownActive { retry_at });
+        }
+    }
+    Ok(())
+}
+
+// checks if the withdrawal stays within the limit of the current window and records it
+fn check_withdraw_limit(
+    storage: &mut dyn Storage,
+    env: &Env,
+    key: &(Addr, String),
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    if let Some(withdrawn) = window_withdrawal(storage, env, key, amount)? {
+        WITHDRAWN.save(storage, key.clone(), &withdrawn)?;
+    }
+    Ok(())
+}
+
+// returns what the current window would have withdrawn with the amount, none if there is no limit
+fn window_withdrawal(
+    storage: &dyn Storage,
+    env: &Env,
+    key: &(Addr, String),
+    amount: Uint128,
+) -> Result<Option<WindowWithdrawal>, ContractError> {
+    let limit = match WITHDRAW_LIMIT.load(storage)? {
+        Some(limit) => limit,
+        None => return Ok(None),
+    };
+    let window = WITHDRAW_WINDOW.load(storage)?;
+    let mut withdrawn = WITHDRAWN
+        .may_load(storage, key.clone())?
+        .filter(|withdrawn| env.block.height < withdrawn.start + window)
+        .unwrap_or(WindowWithdrawal {
+            start: env.block.height,
+            amount: Uint128::zero(),
+        });
+    withdrawn.amount += amount;
+    if withdrawn.amount > limit {
+        return Err(ContractError::WithdrawLimitExceeded {});
+    }
+    Ok(Some(withdrawn))
+}
+
+// sends withdrawn tokens with a reply on failure, so the balance can be restored
+fn send_withdrawal(
+    storage: &mut dyn Storage,
+    env: &Env,
+    recipient: Addr,
+    to: Addr,
+    amount: Vec<Coin>,
+) -> StdResult<Response> {
+    let event = action_event("withdraw", &recipient, &amount);
+    audit(storage, env, "withdraw", &recipient, &amount)?;
+    LAST_WITHDRAW_BLOCK.save(storage, recipient.clone(), &env.block.height)?;
+
+    // queue the withdrawal until the next flush when payouts are batched
+    if BATCH_PAYOUTS.load(storage)? {
+        let payout = PendingPayout {
+            recipient,
+            to: to.clone(),
+            amount,
+        };
+        queue_payout(storage, &payout)?;
+        return Ok(Response::new()
+            .add_event(event)
+            .add_attribute("action", "queue_payout")
+            .add_attribute("to", to));
+    }
+
+    Ok(send_with_rollback(storage, recipient, to, amount, WITHDRAW_REPLY_ID)?.add_event(event))
+}
+
+// adds a withdrawal to the end of the queue
+fn queue_payout(storage: &mut dyn Storage, payout: &PendingPayout) -> StdResult<()> {
+    let id = PAYOUT_COUNT.update(storage, |count| -> StdResult<u64> { Ok(count + 1) })?;
+    PENDING_PAYOUTS.save(storage, id, payout)
+}
+
+fn flush_payouts(
+    deps: DepsMut,
+    info: MessageInfo,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+    check_withdrawals_open(deps.storage)?;
+
+    let limit = limit.unwrap_or(DEFAULT_FLUSH_LIMIT).min(MAX_FLUSH_LIMIT) as usize;
+    let payouts = PENDING_PAYOUTS
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    if payouts.is_empty() {
+        return Err(ContractError::NoPendingPayouts {});
+    }
+
+    // only the payouts of this flush can still fail, forget the earlier ones
+    let flushed = FLUSHED_PAYOUTS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for id in flushed {
+        FLUSHED_PAYOUTS.remove(deps.storage, id);
+    }
+
+    // the balances were deducted when queued, a failing send is credited back on reply
+    let count = payouts.len();
+    let mut res = Response::new();
+    for (id, payout) in payouts {
+        PENDING_PAYOUTS.remove(deps.storage, id);
+        FLUSHED_PAYOUTS.save(deps.storage, id, &payout)?;
+        res = res.add_submessage(SubMsg::reply_on_error(
+            BankMsg::Send {
+                to_address: payout.to.into(),
+                amount: payout.amount,
+            },
+            PAYOUT_REPLY_ID_OFFSET + id,
+        ));
+    }
+
+    Ok(res
+        .add_attribute("method", "flush_payouts")
+        .add_attribute("count", count.to_string()))
+}
+
+fn cancel_pending_payout(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // the entries leave the queue before being credited, so they are restored only once
+    let cancelled = PENDING_PAYOUTS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?
+        .into_iter()
+        .filter(|(_, payout)| payout.recipient == info.sender)
+        .collect::<Vec<_>>();
+    if cancelled.is_empty() {
+        return Err(ContractError::NoPendingPayouts {});
+    }
+
+    for (id, payout) in &cancelled {
+        PENDING_PAYOUTS.remove(deps.storage, *id);
+        for withdrawn in &payout.amount {
+            credit(
+                deps.storage,
+                &info.sender,
+                &withdrawn.denom,
+                withdrawn.amount,
+            )?;
+        }
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "cancel_pending_payout")
+        .add_attribute("cancelled", cancelled.len().to_string()))
+}
+
+// sends tokens already deducted from the state, remembering them so that the
+// reply with the given id can put them back if the send fails. Every send of
+// tokens owed by the contract goes after the state update, never before
+fn send_with_rollback(
+    storage: &mut dyn Storage,
+    recipient: Addr,
+    to: Addr,
+    amount: Vec<Coin>,
+    reply_id: u64,
+) -> StdResult<Response> {
+    let pending = PendingWithdrawal {
+        recipient,
+        amount: amount.clone(),
+    };
+    PENDING_WITHDRAWAL.save(storage, &pending)?;
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_error(
+            BankMsg::Send {
+                to_address: to.clone().into(),
+                amount,
+            },
+            reply_id,
+        ))
+        .add_attribute("action", "withdraw")
+        .add_attribute("to", to))
+}
+
+// computes the coins a withdrawal would send and the balance left afterwards,
+// shared by the withdraw handler and the preview query
+fn compute_withdrawal(
+    deps: Deps,
+    env: &Env,
+    address: &Addr,
+    quantity: Option<Uint128>,
+    denom: Option<String>,
+    percent: Option<u8>,
+) -> Result<PreviewWithdrawResponse, ContractError> {
+    // withdraw the default denom if no denom is present
+    let denom = match denom {
+        Some(denom) => denom,
+        None => CONFIG.load(deps.storage)?.denom,
+    };
+    let key = (address.clone(), denom.clone());
+    let balance = AMOUNTS.load(deps.storage, key.clone())?;
+    let amount = unlocked_amount(deps.storage, env, key.clone(), balance)?;
+
+    // withdraw a percent of the unlocked balance or all of it if no quantity is present
+    let quantity = match (quantity, percent) {
+        (Some(_), Some(_)) => return Err(ContractError::ConflictingWithdrawArgs {}),
+        (Some(quantity), None) => quantity,
+        (None, Some(percent)) => {
+            if percent == 0 || percent > 100 {
+                return Err(ContractError::InvalidPercent {});
+            }
+            amount.multiply_ratio(percent, 100u128)
+        }
+        (None, None) => amount,
+    };
+
+    // a lock only holds back the part it covers
+    if quantity > amount || amount.is_zero() {
+        if let (_, Some(until)) = active_locks(deps.storage, env, key)? {
+            return Err(ContractError::StillLocked { until });
+        }
+    }
+
+    // check if quantity is valid
+    if quantity > amount {
+        return Err(ContractError::ExceededQuantity {});
+    }
+
+    Ok(PreviewWithdrawResponse {
+        amount: vec![coin(quantity.u128(), denom)],
+        remaining: balance - quantity,
+    })
+}
+
+// event with the same keys for every action, for monitoring
+fn action_event(action: &str, address: &Addr, amount: &[Coin]) -> Event {
+    let amount = amount.iter().map(Coin::to_string).collect::<Vec<_>>();
+    Event::new(action)
+        .add_attribute("address", address)
+        .add_attribute("amount", amount.join(","))
+}
+
+// appends an action to the audit log
+fn audit(
+    storage: &mut dyn Storage,
+    env: &Env,
+    action: &str,
+    actor: &Addr,
+    amount: &[Coin],
+) -> StdResult<()> {
+    let id = AUDIT_COUNT.update(storage, |count| -> StdResult<u64> { Ok(count + 1) })?;
+    let entry = AuditEntry {
+        action: action.to_string(),
+        actor: actor.clone(),
+        amount: amount.to_vec(),
+        height: env.block.height,
+    };
+    AUDIT_LOG.save(storage, id, &entry)
+}
+
+// counts the split and keeps a record of it along with the recipients of the sender
+fn record_split(
+    storage: &mut dyn Storage,
+    env: &Env,
+    sender: &Addr,
+    recipients: Vec<Addr>,
+    amount: Vec<Coin>,
+    fee: Vec<Coin>,
+) -> StdResult<()> {
+    let count = SPLIT_COUNT.update(storage, |count| -> StdResult<u64> { Ok(count + 1) })?;
+    for recipient in &recipients {
+        SENDER_RECIPIENTS.save(storage, (sender.clone(), recipient.clone()), &())?;
+    }
+    let record = DepositRecord {
+        sender: sender.clone(),
+        recipients,
+        amount,
+        fee,
+        height: env.block.height,
+        time: env.block.time,
+    };
+    HISTORY.save(storage, count, &record)
+}
+
+// returns the withdrawals of a denom queued for the next flush
+fn queued_payouts(storage: &dyn Storage, denom: &str) -> StdResult<Uint128> {
+    let mut total = Uint128::zero();
+    for payout in PENDING_PAYOUTS.range(storage, None, None, Order::Ascending) {
+        let (_, payout) = payout?;
+        for queued in payout.amount {
+            if queued.denom == denom {
+                total += queued.amount;
+            }
+        }
+    }
+    Ok(total)
+}
+
+// this is a helper to move the tokens, so the business logic is easy to read
+fn send_tokens(to_address: Addr, amount: Vec<Coin>, action: &str) -> Response {
+    Response::new()
+        .add_message(BankMsg::Send {
+            to_address: to_address.clone().into(),
+            amount,
+        })
+        .add_attribute("action", action)
+        .add_attribute("to", to_address)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(deps: DepsMut, env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    match msg {
+        SudoMsg::SetFeeRate { fee_bps } => {
+            let res = save_fee_rate(deps.storage, &env, fee_bps)?;
+            // governance calls sudo as the chain itself, recorded as the contract
+            audit(
+                deps.storage,
+                &env,
+                "set_fee_rate",
+                &env.contract.address,
+                &[],
+            )?;
+            Ok(res)
+        }
+        SudoMsg::SetPaused { paused } => {
+            let res = save_paused(deps.storage, paused)?;
+            audit(deps.storage, &env, "set_paused", &env.contract.address, &[])?;
+            Ok(res)
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let stored = get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::WrongContract {
+            contract: stored.contract,
+        });
+    }
+
+    // only upgrade, or migrate again to the same version
+    let stored_version = parse_version(&stored.version)?;
+    let version = parse_version(CONTRACT_VERSION)?;
+    if stored_version > version {
+        return Err(ContractError::CannotDowngrade {
+            stored: stored.version,
+        });
+    }
+
+    // the first release kept a single-denom balance map and fee item under the keys the
+    // multi-denom ones use now, those cannot be read as the current layout
+    if CONFIG.may_load(deps.storage)?.is_none() {
+        return Err(ContractError::UnsupportedLayout {});
+    }
+    set_contract_version(deps.storage, CONTRACT_NAME, version.to_string())?;
+    rebuild_liabilities(deps.storage)?;
+    if PAYOUT_COUNT.may_load(deps.storage)?.is_none() {
+        PAYOUT_COUNT.save(deps.storage, &0)?;
+    }
+    if DECIMALS.may_load(deps.storage)?.is_none() {
+        DECIMALS.save(deps.storage, &DEFAULT_DECIMALS)?;
+    }
+    if ADMINS.may_load(deps.storage)?.is_none() {
+        ADMINS.save(deps.storage, &vec![])?;
+    }
+    if REFERRER_BPS.may_load(deps.storage)?.is_none() {
+        REFERRER_BPS.save(deps.storage, &0)?;
+    }
+    if TRACK_DUST.may_load(deps.storage)?.is_none() {
+        TRACK_DUST.save(deps.storage, &false)?;
+    }
+    if WITHDRAW_COOLDOWN.may_load(deps.storage)?.is_none() {
+        WITHDRAW_COOLDOWN.save(deps.storage, &0)?;
+    }
+    if MAX_RECIPIENTS.may_load(deps.storage)?.is_none() {
+        MAX_RECIPIENTS.save(deps.storage, &DEFAULT_MAX_RECIPIENTS)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("from_version", stored.version)
+        .add_attribute("to_version", version.to_string()))
+}
+
+// sums the balances again, as older versions did not keep the liabilities
+fn rebuild_liabilities(storage: &mut dyn Storage) -> StdResult<()> {
+    let mut totals: Vec<(String, Uint128)> = vec![];
+    for item in AMOUNTS.range(storage, None, None, Order::Ascending) {
+        let ((_, denom), amount) = item?;
+        match totals
+            .iter_mut()
+            .find(|(total_denom, _)| *total_denom == denom)
+        {
+            Some((_, total)) => *total += amount,
+            None => totals.push((denom, amount)),
+        }
+    }
+    let stale = TOTAL_LIABILITIES
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for denom in stale {
+        TOTAL_LIABILITIES.remove(storage, denom);
+    }
+    for (denom, total) in totals {
+        TOTAL_LIABILITIES.save(storage, denom, &total)?;
+    }
+    Ok(())
+}
+
+// parses a contract version, which has to be semver to be compared on migrate
+fn parse_version(version: &str) -> Result<Version, ContractError> {
+    Version::parse(version).map_err(|_| ContractError::InvalidVersion {
+        version: version.to_string(),
+    })
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        WITHDRAW_REPLY_ID => restore_withdrawal(deps, env, msg.result),
+        WITHDRAW_FEES_REPLY_ID => restore_fees(deps, env, msg.result),
+        id if id > PAYOUT_REPLY_ID_OFFSET => {
+            restore_payout(deps, env, id - PAYOUT_REPLY_ID_OFFSET, msg.result)
+        }
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+// the bank send of a withdrawal failed, credit the amount back to the recipient
+fn restore_withdrawal(
+    deps: DepsMut,
+    env: Env,
+    result: SubMsgResult,
+) -> Result<Response, ContractError> {
+    let pending = PENDING_WITHDRAWAL.load(deps.storage)?;
+    PENDING_WITHDRAWAL.remove(deps.storage);
+
+    for withdrawn in &pending.amount {
+        credit(
+            deps.storage,
+            &pending.recipient,
+            &withdrawn.denom,
+            withdrawn.amount,
+        )?;
+    }
+    audit(
+        deps.storage,
+        &env,
+        "restore_withdrawal",
+        &pending.recipient,
+        &pending.amount,
+    )?;
+
+    let amount: Vec<String> = pending.amount.iter().map(Coin::to_string).collect();
+    let mut res = Response::new()
+        .add_attribute("action", "restore_withdrawal")
+        .add_attribute("to", pending.recipient)
+        .add_attribute("amount", amount.join(","));
+    if let SubMsgResult::Err(err) = result {
+        res = res.add_attribute("error", err);
+    }
+    Ok(res)
+}
+
+// the bank send of a flushed payout failed, credit the amount back to the recipient
+fn restore_payout(
+    deps: DepsMut,
+    env: Env,
+    id: u64,
+    result: SubMsgResult,
+) -> Result<Response, ContractError> {
+    let payout = FLUSHED_PAYOUTS.load(deps.storage, id)?;
+    FLUSHED_PAYOUTS.remove(deps.storage, id);
+
+    for withdrawn in &payout.amount {
+        credit(
+            deps.storage,
+            &payout.recipient,
+            &withdrawn.denom,
+            withdrawn.amount,
+        )?;
+    }
+    audit(
+        deps.storage,
+        &env,
+        "restore_payout",
+        &payout.recipient,
+        &payout.amount,
+    )?;
+
+    let amount: Vec<String> = payout.amount.iter().map(Coin::to_string).collect();
+    let mut res = Response::new()
+        .add_attribute("action", "restore_payout")
+        .add_attribute("to", payout.recipient)
+        .add_attribute("amount", amount.join(","));
+    if let SubMsgResult::Err(err) = result {
+        res = res.add_attribute("error", err);
+    }
+    Ok(res)
+}
+
+// the bank send of a fee withdrawal failed, put the amount back in the fee pot
+fn restore_fees(deps: DepsMut, env: Env, result: SubMsgResult) -> Result<Response, ContractError> {
+    let pending = PENDING_WITHDRAWAL.load(deps.storage)?;
+    PENDING_WITHDRAWAL.remove(deps.storage);
+
+    for withdrawn in &pending.amount {
+        add_fee(deps.storage, &withdrawn.denom, withdrawn.amount)?;
+    }
+    audit(
+        deps.storage,
+        &env,
+        "restore_fees",
+        &pending.recipient,
+        &pending.amount,
+    )?;
+
+    let amount: Vec<String> = pending.amount.iter().map(Coin::to_string).collect();
+    let mut res = Response::new()
+        .add_attribute("action", "restore_fees")
+        .add_attribute("amount", amount.join(","));
+    if let SubMsgResult::Err(err) = result {
+        res = res.add_attribute("error", err);
+    }
+    Ok(res)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::OwnerQuery {} => to_binary(&query_state(deps)?),
+        QueryMsg::PendingOwner {} => to_binary(&PENDING_OWNER.may_load(deps.storage)?),
+        QueryMsg::WithdrawableAmount { address, denom } => {
+            to_binary(&withdrawable_amount(deps, env, address, denom)?)
+        }
+        QueryMsg::WithdrawableAmounts { addresses, denom } => {
+            to_binary(&withdrawable_amounts(deps, env, addresses, denom)?)
+        }
+        QueryMsg::Stats {} => to_binary(&query_stats(deps)?),
+        QueryMsg::MapSize {} => to_binary(&MAP_SIZE.load(deps.storage)?),
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::FullConfig {} => to_binary(&query_full_config(deps)?),
+        QueryMsg::ExportBalances { start_after, limit } => {
+            to_binary(&export_balances(deps, start_after, limit)?)
+        }
+        QueryMsg::TotalFees {} => to_binary(&total_fees(deps)?),
+        QueryMsg::TotalLiabilities {} => to_binary(&total_liabilities(deps)?),
+        QueryMsg::Dust {} => to_binary(&query_dust(deps)?),
+        QueryMsg::VestingInfo { address, denom } => {
+            to_binary(&vesting_info(deps, env, address, denom)?)
+        }
+        QueryMsg::DisplayBalance { address } => {
+            let amount = withdrawable_amount(deps, env, address, None)?;
+            to_binary(&display_amount(deps, amount)?)
+        }
+        QueryMsg::DisplayFees {} => {
+            let denom = CONFIG.load(deps.storage)?.denom;
+            let fee = FEE_BY_DENOM.may_load(deps.storage, denom)?;
+            to_binary(&display_amount(deps, fee.unwrap_or_default())?)
+        }
+        QueryMsg::NextNonce { address } => {
+            to_binary(&NONCES.may_load(deps.storage, address)?.unwrap_or_default())
+        }
+        QueryMsg::TotalEarned { address, denom } => to_binary(&total_earned(deps, address, denom)?),
+        QueryMsg::Status {} => to_binary(&query_status(deps)?),
+        QueryMsg::Roles { address } => to_binary(&query_roles(deps, address)?),
+        QueryMsg::ContractBalance {} => to_binary(&contract_balance(deps, env)?),
+        QueryMsg::Solvency {} => to_binary(&solvency(deps, env)?),
+        QueryMsg::PreviewWithdraw {
+            address,
+            quantity,
+            denom,
+            percent,
+        } => to_binary(&preview_withdraw(
+            deps, env, address, quantity, denom, percent,
+        )?),
+        QueryMsg::SimulateSplit { amount, recipients } => {
+            to_binary(&simulate_split(deps, amount, recipients)?)
+        }
+        QueryMsg::SimulateWithdraw { address, quantity } => {
+            to_binary(&simulate_withdraw(deps, env, address, quantity)?)
+        }
+        QueryMsg::History { start_after, limit } => {
+            to_binary(&query_history(deps, start_after, limit)?)
+        }
+        QueryMsg::RecipientsBySender {
+            sender,
+            start_after,
+            limit,
+        } => to_binary(&recipients_by_sender(deps, sender, start_after, limit)?),
+        QueryMsg::FeeRateHistory { start_after, limit } => {
+            to_binary(&fee_rate_history(deps, start_after, limit)?)
+        }
+        QueryMsg::AuditLog { start_after, limit } => {
+            to_binary(&audit_log(deps, start_after, limit)?)
+        }
+    }
+}
+
+fn query_state(deps: Deps) -> StdResult<State> {
+    // returns the state
+    STATE.load(deps.storage)
+}
+
+// returns the activity statistics
+fn query_stats(deps: Deps) -> StdResult<StatsResponse> {
+    Ok(StatsResponse {
+        split_count: SPLIT_COUNT.load(deps.storage)?,
+        recipient_count: RECIPIENT_COUNT.load(deps.storage)?,
+    })
+}
+
+// returns the owner with the denom and fee configuration
+fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(ConfigResponse {
+        owner: STATE.load(deps.storage)?.owner,
+        denom: config.denom,
+        fee_bps: config.fee_bps,
+        fee_percent: fee_percent(config.fee_bps),
+        min_amount: config.min_amount,
+        allowed_denoms: ALLOWED_DENOMS.load(deps.storage)?,
+        fee_recipient: config.fee_recipient,
+        fee_rounding: config.fee_rounding,
+    })
+}
+
+// formats a fee rate in basis points as a percentage with two decimals
+fn fee_percent(fee_bps: u16) -> String {
+    format!("{}.{:02}", fee_bps / 100, fee_bps % 100)
+}
+
+// returns every operational parameter
+fn query_full_config(deps: Deps) -> StdResult<FullConfigResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(FullConfigResponse {
+        owner: STATE.load(deps.storage)?.owner,
+        pending_owner: PENDING_OWNER.may_load(deps.storage)?,
+        admins: ADMINS.load(deps.storage)?,
+        denom: config.denom,
+        fee_bps: config.fee_bps,
+        min_fee: MIN_FEE.load(deps.storage)?,
+        referrer_bps: REFERRER_BPS.load(deps.storage)?,
+        min_amount: config.min_amount,
+        max_deposit: MAX_DEPOSIT.load(deps.storage)?,
+        allowed_denoms: ALLOWED_DENOMS.load(deps.storage)?,
+        fee_recipient: config.fee_recipient,
+        fee_rounding: config.fee_rounding,
+        fee_auto_forward_threshold: FEE_AUTO_FORWARD_THRESHOLD.load(deps.storage)?,
+        reclaim_window: RECLAIM_WINDOW.load(deps.storage)?,
+        withdraw_limit: WITHDRAW_LIMIT.load(deps.storage)?,
+        withdraw_window: WITHDRAW_WINDOW.load(deps.storage)?,
+        withdraw_cooldown: WITHDRAW_COOLDOWN.load(deps.storage)?,
+        min_withdrawal: MIN_WITHDRAWAL.load(deps.storage)?,
+        max_recipients: MAX_RECIPIENTS.load(deps.storage)?,
+        batch_payouts: BATCH_PAYOUTS.load(deps.storage)?,
+        track_dust: TRACK_DUST.load(deps.storage)?,
+        decimals: DECIMALS.load(deps.storage)?,
+        paused: PAUSED.load(deps.storage)?,
+        withdrawals_frozen: WITHDRAWALS_FROZEN.load(deps.storage)?,
+        finalized: FINALIZED.load(deps.storage)?,
+    })
+}
+
+// returns the lifecycle flags of the contract
+fn query_status(deps: Deps) -> StdResult<StatusResponse> {
+    Ok(StatusResponse {
+        paused: PAUSED.load(deps.storage)?,
+        withdrawals_frozen: WITHDRAWALS_FROZEN.load(deps.storage)?,
+        finalized: FINALIZED.load(deps.storage)?,
+    })
+}
+
+// returns the roles an address holds
+fn query_roles(deps: Deps, address: Addr) -> StdResult<RolesResponse> {
+    Ok(RolesResponse {
+        is_owner: STATE.load(deps.storage)?.owner == address,
+        is_admin: ADMINS.load(deps.storage)?.contains(&address),
+        is_fee_recipient: CONFIG.load(deps.storage)?.fee_recipient == address,
+        is_blocked: BLOCKED.has(deps.storage, address),
+    })
+}
+
+// returns the native balance the contract holds in the default denom
+fn contract_balance(deps: Deps, env: Env) -> StdResult<Coin> {
+    let denom = CONFIG.load(deps.storage)?.denom;
+    deps.querier.query_balance(env.contract.address, denom)
+}
+
+// compares the balances and fees owed in the default denom against the native balance
+fn solvency(deps: Deps, env: Env) -> StdResult<SolvencyResponse> {
+    let denom = CONFIG.load(deps.storage)?.denom;
+
+    let dust = DUST
+        .may_load(deps.storage, denom.clone())?
+        .unwrap_or_default();
+    let liabilities = FEE_BY_DENOM
+        .may_load(deps.storage, denom.clone())?
+        .unwrap_or_default()
+        + liabilities(deps.storage, &denom)?
+        + queued_payouts(deps.storage, &denom)?
+        + dust;
+
+    let holdings = deps
+        .querier
+        .query_balance(env.contract.address, denom)?
+        .amount;
+    Ok(SolvencyResponse {
+        liabilities,
+        holdings,
+        solvent: holdings >= liabilities,
+    })
+}
+
+// returns the unlocked amount for an address in a denom, the default denom if not set
+fn withdrawable_amount(
+    deps: Deps,
+    env: Env,
+    address: Addr,
+    denom: Option<String>,
+) -> StdResult<Uint128> {
+    let denom = match denom {
+        Some(denom) => denom,
+        None => CONFIG.load(deps.storage)?.denom,
+    };
+    let key = (address, denom);
+    let amount = AMOUNTS.may_load(deps.storage, key.clone())?;
+    unlocked_amount(deps.storage, &env, key, amount.unwrap_or_default())
+}
+
+// returns the balances owed to recipients in every denom
+fn total_liabilities(deps: Deps) -> StdResult<TotalLiabilitiesResponse> {
+    let liabilities = TOTAL_LIABILITIES
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| !matches!(item, Ok((_, total)) if total.is_zero()))
+        .map(|item| item.map(|(denom, total)| coin(total.u128(), denom)))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(TotalLiabilitiesResponse { liabilities })
+}
+
+// returns the dust collected in every denom
+fn query_dust(deps: Deps) -> StdResult<Vec<Coin>> {
+    DUST.range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, dust)| coin(dust.u128(), denom)))
+        .collect()
+}
+
+// returns the fees collected in every denom
+fn total_fees(deps: Deps) -> StdResult<TotalFeesResponse> {
+    let fees = FEE_BY_DENOM
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, fee)| coin(fee.u128(), denom)))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(TotalFeesResponse { fees })
+}
+
+fn vesting_info(
+    deps: Deps,
+    env: Env,
+    address: Addr,
+    denom: Option<String>,
+) -> StdResult<Option<VestingInfoResponse>> {
+    let denom = match denom {
+        Some(denom) => denom,
+        None => CONFIG.load(deps.storage)?.denom,
+    };
+    let vesting = match VESTING.may_load(deps.storage, (address, denom))? {
+        Some(vesting) => vesting,
+        None => return Ok(None),
+    };
+    let locked = locked_amount(&vesting, &env);
+    Ok(Some(VestingInfoResponse {
+        total: vesting.amount,
+        unlocked: vesting.amount - locked,
+        locked,
+        start: vesting.start,
+        duration: vesting.duration,
+    }))
+}
+
+// returns the balances in the default denom after the start_after address
+fn export_balances(
+    deps: Deps,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+) -> StdResult<ExportBalancesResponse> {
+    let limit = limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .min(MAX_HISTORY_LIMIT) as usize;
+    let denom = CONFIG.load(deps.storage)?.denom;
+    let min = start_after.map(|address| Bound::exclusive((address, denom.clone())));
+    let entries = AMOUNTS
+        .range(deps.storage, min, None, Order::Ascending)
+        // keep errors so that they are returned
+        .filter(|entry| match entry {
+            Ok(((_, entry_denom), _)) => *entry_denom == denom,
+            Err(_) => true,
+        })
+        .take(limit)
+        .map(|entry| entry.map(|((address, _), amount)| (address, amount)))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(ExportBalancesResponse { entries })
+}
+
+// pairs an amount of the default denom with its value in whole tokens
+fn display_amount(deps: Deps, raw: Uint128) -> StdResult<DisplayAmount> {
+    let decimals = DECIMALS.load(deps.storage)?;
+    Ok(DisplayAmount {
+        raw,
+        display: format_decimals(raw, decimals),
+    })
+}
+
+// returns everything ever split to the address, zero if never funded
+fn total_earned(deps: Deps, address: Addr, denom: Option<String>) -> StdResult<Uint128> {
+    let denom = match denom {
+        Some(denom) => denom,
+        None => CONFIG.load(deps.storage)?.denom,
+    };
+    let earned = TOTAL_EARNED.may_load(deps.storage, (address, denom))?;
+    Ok(earned.unwrap_or_default())
+}
+
+// returns the unlocked amount for every address, zero if not funded
+fn withdrawable_amounts(
+    deps: Deps,
+    env: Env,
+    addresses: Vec<Addr>,
+    denom: Option<String>,
+) -> StdResult<Vec<(Addr, Uint128)>> {
+    if addresses.len() > MAX_BATCH_ADDRESSES {
+        return Err(StdError::generic_err(format!(
+            "At most {} addresses can be queried at once",
+            MAX_BATCH_ADDRESSES
+        )));
+    }
+    let denom = match denom {
+        Some(denom) => denom,
+        None => CONFIG.load(deps.storage)?.denom,
+    };
+    addresses
+        .into_iter()
+        .map(|address| {
+            let key = (address.clone(), denom.clone());
+            let amount = AMOUNTS.may_load(deps.storage, key.clone())?;
+            let amount = unlocked_amount(deps.storage, &env, key, amount.unwrap_or_default())?;
+            Ok((address, amount))
+        })
+        .collect()
+}
+
+// returns the coins a withdrawal would send without executing it
+fn preview_withdraw(
+    deps: Deps,
+    env: Env,
+    address: Addr,
+    quantity: Option<Uint128>,
+    denom: Option<String>,
+    percent: Option<u8>,
+) -> StdResult<PreviewWithdrawResponse> {
+    compute_withdrawal(deps, &env, &address, quantity, denom, percent).map_err(|err| match err {
+        ContractError::Std(err) => err,
+        err => StdError::generic_err(err.to_string()),
+    })
+}
+
+// returns the outcome of a withdrawal without executing it
+fn simulate_withdraw(
+    deps: Deps,
+    env: Env,
+    address: Addr,
+    quantity: Option<Uint128>,
+) -> StdResult<WithdrawResponse> {
+    let withdrawal = validate_withdrawal(deps, &env, &address, quantity, None, None).map_err(
+        |err| match err {
+            ContractError::Std(err) => err,
+            err => StdError::generic_err(err.to_string()),
+        },
+    )?;
+    Ok(WithdrawResponse {
+        withdrawn: withdrawal.amount[0].clone(),
+        remaining: withdrawal.remaining,
+    })
+}
+
+// returns the outcome of splitting an amount without executing it
+fn simulate_split(
+    deps: Deps,
+    amount: Uint128,
+    recipients: u32,
+) -> StdResult<SimulateSplitResponse> {
+    check_recipient_count(deps.storage, recipients as usize)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    // the default denom may have its own fee rate
+    let mut config = CONFIG.load(deps.storage)?;
+    if let Some(fee_bps) = DENOM_FEE_BPS.may_load(deps.storage, config.denom.clone())? {
+        config.fee_bps = fee_bps;
+    }
+    let min_fee = MIN_FEE.load(deps.storage)?;
+    let shares = compute_split(amount.u128(), &config, min_fee.u128(), recipients);
+    let shares = check_split(shares).map_err(|err| StdError::generic_err(err.to_string()))?;
+    Ok(SimulateSplitResponse {
+        fee: collected_fee(&shares, TRACK_DUST.load(deps.storage)?),
+        amount_each: Uint128::new(shares.amount_each),
+        remainder: Uint128::new(shares.remainder),
+    })
+}
+
+// returns the split records before start_after, newest first
+fn query_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<HistoryResponse> {
+    let limit = limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .min(MAX_HISTORY_LIMIT) as usize;
+    let max = start_after.map(Bound::exclusive);
+    let records = HISTORY
+        .range(deps.storage, None, max, Order::Descending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(HistoryResponse { records })
+}
+
+// returns the audit entries after the start_after id, oldest first
+fn audit_log(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<AuditLogResponse> {
+    let limit = limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .min(MAX_HISTORY_LIMIT) as usize;
+    let min = start_after.map(Bound::exclusive);
+    let entries = AUDIT_LOG
+        .range(deps.storage, min, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(AuditLogResponse { entries })
+}
+
+// returns the fee rate changes after the start_after height, oldest first
+fn fee_rate_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<FeeRateHistoryResponse> {
+    let limit = limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .min(MAX_HISTORY_LIMIT) as usize;
+    let min = start_after.map(Bound::exclusive);
+    let changes = FEE_RATE_HISTORY
+        .range(deps.storage, min, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(FeeRateHistoryResponse { changes })
+}
+
+// returns the recipients a sender has split to after start_after, ordered by address
+fn recipients_by_sender(
+    deps: Deps,
+    sender: Addr,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+) -> StdResult<RecipientsResponse> {
+    let limit = limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .min(MAX_HISTORY_LIMIT) as usize;
+    let min = start_after.map(Bound::exclusive);
+    let recipients = SENDER_RECIPIENTS
+        .prefix(sender)
+        .keys(deps.storage, min, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(RecipientsResponse { recipients })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::OwnerResponse;
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_dependencies_with_balance, mock_env, mock_info, MOCK_CONTRACT_ADDR,
+    };
+    use cosmwasm_std::{attr, coins, from_binary, CosmosMsg};
+
+    // checks if initialization was successful
+    #[test]
+    fn proper_initialization() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg::default();
+        let info = mock_info("creator", &coins(0, "usei"));
+
+        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::OwnerQuery {}).unwrap();
+        let value: OwnerResponse = from_binary(&res).unwrap();
+
+        // check if the owner is set after initialization
+        assert_eq!(info.sender, value.owner);
+    }
+
+    // checks if the initial balances are seeded at instantiation
+    #[test]
+    fn initial_balances() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            initial_balances: vec![
+                (Addr::unchecked("person1"), Uint128::new(40)),
+                (Addr::unchecked("person2"), Uint128::new(60)),
+            ],
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        for (person, expected) in [("person1", 40), ("person2", 60)] {
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::WithdrawableAmount {
+                    address: Addr::unchecked(person),
+                    denom: None,
+                },
+            )
+            .unwrap();
+            let user_balance: Uint128 = from_binary(&res).unwrap();
+            assert_eq!(Uint128::new(expected), user_balance);
+        }
+
+        // duplicate addresses are rejected
+        let mut deps = mock_dependencies();
+        let instantiate_msg = InstantiateMsg {
+            initial_balances: vec![
+                (Addr::unchecked("person1"), Uint128::new(40)),
+                (Addr::unchecked("person1"), Uint128::new(60)),
+            ],
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg);
+        match res.unwrap_err() {
+            ContractError::DuplicateInitialBalance { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if the fee rate is also shown as a percentage
+    #[test]
+    fn config_fee_percent() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            fee_bps: Some(150),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let config: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!("1.50", config.fee_percent);
+
+        for (fee_bps, percent) in [(0, "0.00"), (5, "0.05"), (100, "1.00"), (10_000, "100.00")] {
+            assert_eq!(percent, fee_percent(fee_bps));
+        }
+    }
+
+    // checks if the config matches the instantiation parameters
+    #[test]
+    fn config() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            denom: Some("uatom".to_string()),
+            fee_bps: Some(250),
+            min_amount: Some(Uint128::new(1000)),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let config: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            ConfigResponse {
+                owner: Addr::unchecked("creator"),
+                denom: "uatom".to_string(),
+                fee_bps: 250,
+                fee_percent: "2.50".to_string(),
+                min_amount: Uint128::new(1000),
+                allowed_denoms: vec!["uatom".to_string()],
+                fee_recipient: Addr::unchecked("creator"),
+                fee_rounding: RoundingMode::Floor,
+            },
+            config
+        );
+
+        // splits below the minimum amount are rejected
+        let sender_info = mock_info("sender", &coins(999, "uatom"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg);
+        match res.unwrap_err() {
+            ContractError::AmountBelowMinimum { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if every parameter is returned in one query
+    #[test]
+    fn full_config() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            denom: Some("uatom".to_string()),
+            fee_bps: Some(250),
+            min_amount: Some(Uint128::new(1000)),
+            reclaim_window: Some(20),
+            allowed_denoms: Some(vec!["uatom".to_string(), "usei".to_string()]),
+            fee_recipient: Some(Addr::unchecked("treasury")),
+            fee_rounding: Some(RoundingMode::Ceil),
+            min_fee: Some(Uint128::new(5)),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::FullConfig {}).unwrap();
+        let config: FullConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            FullConfigResponse {
+                owner: Addr::unchecked("creator"),
+                pending_owner: None,
+                admins: vec![],
+                denom: "uatom".to_string(),
+                fee_bps: 250,
+                min_fee: Uint128::new(5),
+                referrer_bps: 0,
+                min_amount: Uint128::new(1000),
+                max_deposit: None,
+                allowed_denoms: vec!["uatom".to_string(), "usei".to_string()],
+                fee_recipient: Addr::unchecked("treasury"),
+                fee_rounding: RoundingMode::Ceil,
+                fee_auto_forward_threshold: None,
+                reclaim_window: Some(20),
+                withdraw_limit: None,
+                withdraw_window: 0,
+                withdraw_cooldown: 0,
+                min_withdrawal: Uint128::zero(),
+                max_recipients: 20,
+                batch_payouts: false,
+                track_dust: false,
+                decimals: 6,
+                paused: false,
+                withdrawals_frozen: false,
+                finalized: false,
+            },
+            config
+        );
+    }
+
+    // checks if the status follows each lifecycle flag
+    #[test]
+    fn status() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Status {}).unwrap();
+        let status: StatusResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            StatusResponse {
+                paused: false,
+                withdrawals_frozen: false,
+                finalized: false,
+            },
+            status
+        );
+
+        // every flag is turned on one after the other
+        for (msg, expected) in [
+            (ExecuteMsg::SetPaused { paused: true }, (true, false, false)),
+            (
+                ExecuteMsg::SetWithdrawalsFrozen { frozen: true },
+                (true, true, false),
+            ),
+            (ExecuteMsg::Finalize {}, (true, true, true)),
+        ] {
+            let _res = execute(deps.as_mut(), mock_env(), creator_info.clone(), msg).unwrap();
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::Status {}).unwrap();
+            let status: StatusResponse = from_binary(&res).unwrap();
+            assert_eq!(
+                StatusResponse {
+                    paused: expected.0,
+                    withdrawals_frozen: expected.1,
+                    finalized: expected.2,
+                },
+                status
+            );
+        }
+    }
+
+    // checks if the roles of an address are reported
+    #[test]
+    fn roles() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            fee_recipient: Some(Addr::unchecked("treasury")),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+        let block_msg = ExecuteMsg::BlockAddress {
+            address: Addr::unchecked("spammer"),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, block_msg).unwrap();
+
+        for (address, expected) in [
+            ("creator", (true, false, false)),
+            ("treasury", (false, true, false)),
+            ("spammer", (false, false, true)),
+            ("person1", (false, false, false)),
+        ] {
+            let query_msg = QueryMsg::Roles {
+                address: Addr::unchecked(address),
+            };
+            let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+            let roles: RolesResponse = from_binary(&res).unwrap();
+            assert_eq!(
+                RolesResponse {
+                    is_owner: expected.0,
+                    is_admin: false,
+                    is_fee_recipient: expected.1,
+                    is_blocked: expected.2,
+                },
+                roles
+            );
+        }
+    }
+
+    // checks if a denom of the wrong length or format is rejected at instantiation
+    #[test]
+    fn instantiate_denom_format() {
+        let creator_info = mock_info("creator", &[]);
+        let ibc_denom = format!("ibc/{}", "A".repeat(64));
+        let overlong_denom = "u".repeat(129);
+        for (denom, valid) in [
+            ("uatom", true),
+            (ibc_denom.as_str(), true),
+            ("", false),
+            (overlong_denom.as_str(), false),
+            ("1usei", false),
+            ("u sei", false),
+        ] {
+            let mut deps = mock_dependencies();
+            let instantiate_msg = InstantiateMsg {
+                denom: Some(denom.to_string()),
+                allowed_denoms: Some(vec![denom.to_string()]),
+                ..InstantiateMsg::default()
+            };
+            let res = instantiate(
+                deps.as_mut(),
+                mock_env(),
+                creator_info.clone(),
+                instantiate_msg,
+            );
+            match res {
+                Ok(_) if valid => {}
+                Err(ContractError::InvalidDenom {}) if !valid => {}
+                res => panic!("unexpected result for {:?}: {:?}", denom, res),
+            }
+        }
+    }
+
+    // checks if the fee recipient can be required to differ from the owner
+    #[test]
+    fn distinct_fee_recipient() {
+        // lenient mode allows the owner to collect the fees
+        let mut deps = mock_dependencies();
+        let instantiate_msg = InstantiateMsg {
+            fee_recipient: Some(Addr::unchecked("creator")),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // strict mode rejects the owner as fee recipient, also when defaulted
+        for fee_recipient in [Some(Addr::unchecked("creator")), None] {
+            let mut deps = mock_dependencies();
+            let instantiate_msg = InstantiateMsg {
+                fee_recipient,
+                require_distinct_fee_recipient: true,
+                ..InstantiateMsg::default()
+            };
+            let res = instantiate(
+                deps.as_mut(),
+                mock_env(),
+                creator_info.clone(),
+                instantiate_msg,
+            );
+            match res.unwrap_err() {
+                ContractError::OwnerIsFeeRecipient { .. } => {}
+                e => panic!("unexpected error: {:?}", e),
+            }
+        }
+
+        // strict mode accepts a separate treasury, which receives the fees
+        let mut deps = mock_dependencies();
+        let instantiate_msg = InstantiateMsg {
+            fee_recipient: Some(Addr::unchecked("treasury")),
+            require_distinct_fee_recipient: true,
+            ..InstantiateMsg::default()
+        };
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        let execute_res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info,
+            ExecuteMsg::WithdrawFees {
+                amount: None,
+                denom: None,
+            },
+        )
+        .unwrap();
+        let sub_msg = execute_res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "treasury".into(),
+                amount: coins(2, "usei"),
+            })
+        );
+    }
+
+    // checks if the native balance of the contract is returned
+    #[test]
+    fn contract_balance() {
+        let mut deps = mock_dependencies_with_balance(&[coin(500, "usei"), coin(7, "uatom")]);
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::ContractBalance {}).unwrap();
+        let balance: Coin = from_binary(&res).unwrap();
+        assert_eq!(coin(500, "usei"), balance);
+    }
+
+    // checks if the liabilities are compared against the holdings
+    #[test]
+    fn solvency() {
+        let mut deps = mock_dependencies_with_balance(&coins(200, "usei"));
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins, owed as 99 + 99 to the recipients and 2 as fee
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Solvency {}).unwrap();
+        let solvency: SolvencyResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            SolvencyResponse {
+                liabilities: Uint128::new(200),
+                holdings: Uint128::new(200),
+                solvent: true,
+            },
+            solvency
+        );
+
+        // the contract loses part of its balance
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(150, "usei"));
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Solvency {}).unwrap();
+        let solvency: SolvencyResponse = from_binary(&res).unwrap();
+        assert!(!solvency.solvent);
+        assert_eq!(Uint128::new(150), solvency.holdings);
+    }
+
+    // checks if withdrawals queued for the next flush count as liabilities
+    #[test]
+    fn solvency_with_queued_payouts() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            batch_payouts: true,
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 202 coins, owed as 100 + 100 to the recipients and 2 as fee
+        let sender_info = mock_info("sender", &coins(202, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // person1 queues their 100 coins, person2 is still owed 100
+        let user_info = mock_info("person1", &[]);
+        let msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user_info, msg).unwrap();
+
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(150, "usei"));
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Solvency {}).unwrap();
+        let solvency: SolvencyResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            SolvencyResponse {
+                liabilities: Uint128::new(202),
+                holdings: Uint128::new(150),
+                solvent: false,
+            },
+            solvency
+        );
+    }
+
+    // checks if tokens sent to the contract directly can be reconciled into the fees
+    #[test]
+    fn reconcile_fees() {
+        let mut deps = mock_dependencies_with_balance(&coins(200, "usei"));
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins, owed as 99 + 99 to the recipients and 2 as fee
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // 50 stray coins land in the contract
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(250, "usei"));
+
+        // only the owner can reconcile
+        let user_info = mock_info("person1", &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            user_info,
+            ExecuteMsg::ReconcileFees {},
+        );
+        match res.unwrap_err() {
+            ContractError::NotOwner {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        let creator_info = mock_info("creator", &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            ExecuteMsg::ReconcileFees {},
+        )
+        .unwrap();
+        assert!(res.attributes.contains(&attr("adjustment", "50usei")));
+        let fee = FEE_BY_DENOM
+            .load(deps.as_ref().storage, "usei".to_string())
+            .unwrap();
+        assert_eq!(Uint128::new(52), fee);
+
+        // a shortfall never lowers the fee
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(150, "usei"));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info,
+            ExecuteMsg::ReconcileFees {},
+        )
+        .unwrap();
+        assert!(res.attributes.contains(&attr("adjustment", "0usei")));
+        let fee = FEE_BY_DENOM
+            .load(deps.as_ref().storage, "usei".to_string())
+            .unwrap();
+        assert_eq!(Uint128::new(52), fee);
+    }
+
+    // checks if the sent fund was split and balance of recipient updated
+    #[test]
+    fn split_transfer() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // send 200 coins from the sender
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // check the balance of recipient 1
+        let res_1 = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::WithdrawableAmount {
+                address: Addr::unchecked("person1"),
+                denom: None,
+            },
+        )
+        .unwrap();
+        let user_1_balance: Uint128 = from_binary(&res_1).unwrap();
+        assert_eq!(Uint128::new(99), user_1_balance);
+
+        // check the balance of recipient 2
+        let res_2 = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::WithdrawableAmount {
+                address: Addr::unchecked("person2"),
+                denom: None,
+            },
+        )
+        .unwrap();
+        let user_2_balance: Uint128 = from_binary(&res_2).unwrap();
+        assert_eq!(Uint128::new(99), user_2_balance);
+    }
+
+    // checks if every sent denom is split and credited separately
+    #[test]
+    fn split_multiple_denoms() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            allowed_denoms: Some(vec!["usei".to_string(), "uatom".to_string()]),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // send 200 usei and 1000 uatom from the sender
+        let sender_info = mock_info("sender", &[coin(200, "usei"), coin(1000, "uatom")]);
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // check the balance of recipient 1 in both denoms
+        for (denom, expected) in [("usei", 99), ("uatom", 495)] {
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::WithdrawableAmount {
+                    address: Addr::unchecked("person1"),
+                    denom: Some(denom.to_string()),
+                },
+            )
+            .unwrap();
+            let user_balance: Uint128 = from_binary(&res).unwrap();
+            assert_eq!(Uint128::new(expected), user_balance);
+        }
+
+        // person1 withdraws the uatom balance
+        let user_info = mock_info("person1", &[]);
+        let msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: Some("uatom".to_string()),
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let execute_res = execute(deps.as_mut(), mock_env(), user_info, msg).unwrap();
+        let sub_msg = execute_res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "person1".into(),
+                amount: coins(495, "uatom"),
+            })
+        );
+
+        // the owner withdraws the fees of both denoms at once
+        let creator_info = mock_info("creator", &[]);
+        let execute_res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info,
+            ExecuteMsg::WithdrawFees {
+                amount: None,
+                denom: None,
+            },
+        )
+        .unwrap();
+        let sub_msg = execute_res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "creator".into(),
+                amount: vec![coin(10, "uatom"), coin(2, "usei")],
+            })
+        );
+    }
+
+    // checks if only allowed denoms can be split
+    #[test]
+    fn allowed_denoms() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+
+        // only the default denom is allowed at first
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg.clone()).unwrap();
+        let sender_info = mock_info("sender", &coins(1000, "uatom"));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            split_msg.clone(),
+        );
+        match res.unwrap_err() {
+            ContractError::WrongFundCoin { expected, got } => {
+                assert_eq!("usei", expected);
+                assert_eq!("uatom", got);
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // only the owner can allow a denom
+        let add_msg = ExecuteMsg::AddDenom {
+            denom: "uatom".to_string(),
+        };
+        let user_info = mock_info("person1", &[]);
+        let res = execute(deps.as_mut(), mock_env(), user_info, add_msg.clone());
+        match res.unwrap_err() {
+            ContractError::NotOwner { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // a malformed denom cannot be allowed either
+        for denom in [String::new(), "u".repeat(129)] {
+            let bad_msg = ExecuteMsg::AddDenom { denom };
+            let res = execute(deps.as_mut(), mock_env(), creator_info.clone(), bad_msg);
+            match res.unwrap_err() {
+                ContractError::InvalidDenom {} => {}
+                e => panic!("unexpected error: {:?}", e),
+            }
+        }
+
+        // the owner allows uatom and it can be split
+        let _res = execute(deps.as_mut(), mock_env(), creator_info.clone(), add_msg).unwrap();
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            split_msg.clone(),
+        )
+        .unwrap();
+
+        // the owner removes uatom, the balance stays withdrawable but new splits are rejected
+        let remove_msg = ExecuteMsg::RemoveDenom {
+            denom: "uatom".to_string(),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, remove_msg).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg);
+        match res.unwrap_err() {
+            ContractError::WrongFundCoin { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let query_msg = QueryMsg::WithdrawableAmount {
+            address: Addr::unchecked("person1"),
+            denom: Some("uatom".to_string()),
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let value: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(495), value);
+    }
+
+    // checks if only allowed senders can split once an allowlist is set
+    #[test]
+    fn sender_allowlist() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let other_info = mock_info("other", &coins(200, "usei"));
+
+        // anyone can split without an allowlist
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            other_info.clone(),
+            split_msg.clone(),
+        )
+        .unwrap();
+
+        // only the owner can allow a sender
+        let add_msg = ExecuteMsg::AddSender {
+            sender: Addr::unchecked("sender"),
+        };
+        let user_info = mock_info("other", &[]);
+        let res = execute(deps.as_mut(), mock_env(), user_info, add_msg.clone());
+        match res.unwrap_err() {
+            ContractError::NotOwner { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // the owner allows sender, other is denied
+        let _res = execute(deps.as_mut(), mock_env(), creator_info.clone(), add_msg).unwrap();
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            split_msg.clone(),
+        )
+        .unwrap();
+        let res = execute(deps.as_mut(), mock_env(), other_info, split_msg.clone());
+        match res.unwrap_err() {
+            ContractError::SenderNotAllowed { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // the owner removes sender, which is denied as well
+        let remove_msg = ExecuteMsg::RemoveSender {
+            sender: Addr::unchecked("sender"),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, remove_msg).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg);
+        match res.unwrap_err() {
+            ContractError::SenderNotAllowed { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if differently cased forms of an address share one balance
+    #[test]
+    fn normalized_addresses() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender splits to both forms of person1
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("PERSON1"),
+            recipient2: Addr::unchecked("person1"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        assert!(!AMOUNTS.has(
+            deps.as_ref().storage,
+            (Addr::unchecked("PERSON1"), "usei".to_string())
+        ));
+
+        // person1 withdraws both shares from one balance, to a normalized address
+        let user_info = mock_info("person1", &[]);
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: Some(Addr::unchecked("Person1")),
+            to: Some(Addr::unchecked("WALLET")),
+        };
+        let res = execute(deps.as_mut(), mock_env(), user_info, withdraw_msg).unwrap();
+        let sub_msg = res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "wallet".into(),
+                amount: coins(198, "usei"),
+            })
+        );
+
+        // an invalid address is rejected
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("p1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap_err();
+    }
+
+    // checks if a locked balance can only be withdrawn from the lock height on
+    #[test]
+    fn split_lock_until() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // the balances are locked 100 blocks ahead
+        let lock_until = mock_env().block.height + 100;
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: Some(lock_until),
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let withdrawable_msg = QueryMsg::WithdrawableAmount {
+            address: Addr::unchecked("person1"),
+            denom: None,
+        };
+
+        // nothing is withdrawable one block before the lock height
+        let mut env = mock_env();
+        env.block.height = lock_until - 1;
+        let res = query(deps.as_ref(), env.clone(), withdrawable_msg.clone()).unwrap();
+        let withdrawable: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::zero(), withdrawable);
+        let user_info = mock_info("person1", &[]);
+        let res = execute(deps.as_mut(), env, user_info, withdraw_msg.clone());
+        match res.unwrap_err() {
+            ContractError::StillLocked { until } => assert_eq!(lock_until, until),
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // the whole balance is withdrawable at the lock height
+        let mut env = mock_env();
+        env.block.height = lock_until;
+        let res = query(deps.as_ref(), env.clone(), withdrawable_msg).unwrap();
+        let withdrawable: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(99), withdrawable);
+        let user_info = mock_info("person1", &[]);
+        let execute_res = execute(deps.as_mut(), env, user_info, withdraw_msg).unwrap();
+        let sub_msg = execute_res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "person1".into(),
+                amount: coins(99, "usei"),
+            })
+        );
+
+        // a lock height already reached is rejected
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: Some(mock_env().block.height),
+            referrer: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg);
+        match res.unwrap_err() {
+            ContractError::InvalidLockHeight {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if a split only locks its own share of the balance
+    #[test]
+    fn split_lock_own_share() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // person1 gets 99 coins, then 2 coins locked for good by another sender
+        let split_msg = |lock_until: Option<u64>| ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until,
+            referrer: None,
+        };
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg(None)).unwrap();
+        let other_info = mock_info("other", &coins(4, "usei"));
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            other_info,
+            split_msg(Some(u64::MAX)),
+        )
+        .unwrap();
+
+        // the 99 coins stay withdrawable
+        let withdrawable_msg = QueryMsg::WithdrawableAmount {
+            address: Addr::unchecked("person1"),
+            denom: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), withdrawable_msg).unwrap();
+        let withdrawable: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(99), withdrawable);
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let user_info = mock_info("person1", &[]);
+        let execute_res = execute(
+            deps.as_mut(),
+            mock_env(),
+            user_info.clone(),
+            withdraw_msg.clone(),
+        )
+        .unwrap();
+        let sub_msg = execute_res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "person1".into(),
+                amount: coins(99, "usei"),
+            })
+        );
+
+        // only the locked 2 coins are left
+        let mut env = mock_env();
+        env.block.height += 1;
+        let res = execute(deps.as_mut(), env, user_info, withdraw_msg);
+        match res.unwrap_err() {
+            ContractError::StillLocked { until } => assert_eq!(u64::MAX, until),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if a nonce has to be the next one of the sender
+    #[test]
+    fn split_nonce() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        let split_msg = |nonce: u64| ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: Some(nonce),
+            lock_until: None,
+            referrer: None,
+        };
+
+        // the first nonces are 0 and 1
+        for nonce in [0, 1] {
+            let sender_info = mock_info("sender", &coins(200, "usei"));
+            let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg(nonce)).unwrap();
+        }
+
+        // a replayed or skipped nonce is rejected
+        for nonce in [1, 3] {
+            let sender_info = mock_info("sender", &coins(200, "usei"));
+            let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg(nonce));
+            match res.unwrap_err() {
+                ContractError::InvalidNonce { expected } => assert_eq!(2, expected),
+                e => panic!("unexpected error: {:?}", e),
+            }
+        }
+
+        // nonces are scoped per sender
+        let query_msg = QueryMsg::NextNonce {
+            address: Addr::unchecked("sender"),
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let next: u64 = from_binary(&res).unwrap();
+        assert_eq!(2, next);
+        let other_info = mock_info("other", &coins(200, "usei"));
+        let _res = execute(deps.as_mut(), mock_env(), other_info, split_msg(0)).unwrap();
+    }
+
+    // checks if an empty recipient is rejected
+    #[test]
+    fn split_empty_recipient() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        for recipient in ["", "   "] {
+            let sender_info = mock_info("sender", &coins(200, "usei"));
+            let split_msg = ExecuteMsg::Split {
+                recipient1: Addr::unchecked("person1"),
+                recipient2: Addr::unchecked(recipient),
+                memo: None,
+                idempotency_key: None,
+                vesting: None,
+                expires_at: None,
+                fee_on_top: false,
+                coupon: None,
+                nonce: None,
+                lock_until: None,
+                referrer: None,
+            };
+            let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg);
+            match res.unwrap_err() {
+                ContractError::InvalidRecipient {} => {}
+                e => panic!("unexpected error: {:?}", e),
+            }
+        }
+    }
+
+    // checks if a split can be sent to registered aliases
+    #[test]
+    fn split_by_alias() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // person1 registers an alias for itself, which nobody else can take
+        let alias_msg = ExecuteMsg::RegisterAlias {
+            alias: "alice".to_string(),
+            address: None,
+        };
+        let user_info = mock_info("person1", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), user_info, alias_msg.clone()).unwrap();
+        let user_info = mock_info("person2", &[]);
+        let res = execute(deps.as_mut(), mock_env(), user_info, alias_msg);
+        match res.unwrap_err() {
+            ContractError::AliasTaken { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // an unknown alias is rejected
+        let split_msg = ExecuteMsg::SplitByAlias {
+            recipient1: None,
+            recipient1_alias: Some("bob".to_string()),
+            recipient2: Some(Addr::unchecked("person2")),
+            recipient2_alias: None,
+        };
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg);
+        match res.unwrap_err() {
+            ContractError::UnknownAlias { alias } => assert_eq!("bob", alias),
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // sender sends 200 coins to alice (99) and person2 (99)
+        let split_msg = ExecuteMsg::SplitByAlias {
+            recipient1: None,
+            recipient1_alias: Some("alice".to_string()),
+            recipient2: Some(Addr::unchecked("person2")),
+            recipient2_alias: None,
+        };
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        for person in ["person1", "person2"] {
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::WithdrawableAmount {
+                    address: Addr::unchecked(person),
+                    denom: None,
+                },
+            )
+            .unwrap();
+            let user_balance: Uint128 = from_binary(&res).unwrap();
+            assert_eq!(Uint128::new(99), user_balance);
+        }
+    }
+
+    // checks if a recipient with a callback is notified when credited
+    #[test]
+    fn split_callback() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // person1 registers a contract to notify
+        let register_msg = ExecuteMsg::RegisterCallback {
+            contract: Some(Addr::unchecked("notifier")),
+        };
+        let user_info = mock_info("person1", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), user_info, register_msg).unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99), only person1 is notified
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        assert_eq!(1, res.messages.len());
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "notifier".into(),
+                msg: to_binary(&CallbackMsg {
+                    credited: coins(99, "usei"),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+    }
+
+    // checks if a blocked address cannot receive splits or withdraw
+    #[test]
+    fn blocked_recipient() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            split_msg.clone(),
+        )
+        .unwrap();
+
+        // only the owner can block an address, which is normalized
+        let block_msg = ExecuteMsg::BlockAddress {
+            address: Addr::unchecked("PERSON1"),
+        };
+        let user_info = mock_info("person2", &[]);
+        let res = execute(deps.as_mut(), mock_env(), user_info, block_msg.clone());
+        match res.unwrap_err() {
+            ContractError::NotOwner { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // the owner blocks person1, who cannot receive new splits
+        let _res = execute(deps.as_mut(), mock_env(), creator_info.clone(), block_msg).unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            split_msg.clone(),
+        );
+        match res.unwrap_err() {
+            ContractError::RecipientBlocked { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // nor withdraw the balance received before
+        let user_info = mock_info("person1", &[]);
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            user_info.clone(),
+            withdraw_msg.clone(),
+        );
+        match res.unwrap_err() {
+            ContractError::RecipientBlocked { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            user_info.clone(),
+            ExecuteMsg::WithdrawAll {},
+        );
+        match res.unwrap_err() {
+            ContractError::RecipientBlocked { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // once unblocked both work again
+        let unblock_msg = ExecuteMsg::UnblockAddress {
+            address: Addr::unchecked("person1"),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, unblock_msg).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), user_info, withdraw_msg).unwrap();
+        let sub_msg = res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "person1".into(),
+                amount: coins(198, "usei"),
+            })
+        );
+    }
+
+    // checks if the fees can be split across several treasuries
+    #[test]
+    fn withdraw_fees_split() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // sender splits 10000 coins, leaving 100 coins of fees
+        let sender_info = mock_info("sender", &coins(10000, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // shares must sum to 10000 basis points
+        let split_fees_msg = ExecuteMsg::WithdrawFeesSplit {
+            recipients: vec![
+                (Addr::unchecked("treasury1"), 7000),
+                (Addr::unchecked("treasury2"), 2000),
+            ],
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            split_fees_msg,
+        );
+        match res.unwrap_err() {
+            ContractError::InvalidShares { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // every treasury must be a valid address, the fees stay in the pot
+        let split_fees_msg = ExecuteMsg::WithdrawFeesSplit {
+            recipients: vec![
+                (Addr::unchecked("treasury1"), 7000),
+                (Addr::unchecked("x"), 3000),
+            ],
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            split_fees_msg,
+        );
+        match res.unwrap_err() {
+            ContractError::Std { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // only the owner can split the fees
+        let split_fees_msg = ExecuteMsg::WithdrawFeesSplit {
+            recipients: vec![
+                (Addr::unchecked("treasury1"), 7000),
+                (Addr::unchecked("treasury2"), 3000),
+            ],
+        };
+        let user_info = mock_info("person1", &[]);
+        let res = execute(deps.as_mut(), mock_env(), user_info, split_fees_msg.clone());
+        match res.unwrap_err() {
+            ContractError::NotOwner { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // the owner splits the fees 70/30
+        let execute_res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            split_fees_msg.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            execute_res
+                .messages
+                .iter()
+                .map(|sub_msg| sub_msg.msg.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "treasury1".into(),
+                    amount: coins(70, "usei"),
+                }),
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "treasury2".into(),
+                    amount: coins(30, "usei"),
+                }),
+            ]
+        );
+
+        // the pot is empty afterwards
+        let res = execute(deps.as_mut(), mock_env(), creator_info, split_fees_msg);
+        match res.unwrap_err() {
+            ContractError::NoFees { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if split, withdraw and withdraw_fees emit an event with the same keys
+    #[test]
+    fn action_events() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let split_res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let user_info = mock_info("person1", &[]);
+        let withdraw_res = execute(deps.as_mut(), mock_env(), user_info, withdraw_msg).unwrap();
+
+        let fees_msg = ExecuteMsg::WithdrawFees {
+            amount: None,
+            denom: None,
+        };
+        let fees_res = execute(deps.as_mut(), mock_env(), creator_info, fees_msg).unwrap();
+
+        for (res, action, address, amount) in [
+            (split_res, "split", "sender", "200usei"),
+            (withdraw_res, "withdraw", "person1", "99usei"),
+            (fees_res, "withdraw_fees", "creator", "2usei"),
+        ] {
+            assert_eq!(
+                vec![Event::new(action)
+                    .add_attribute("address", address)
+                    .add_attribute("amount", amount)],
+                res.events
+            );
+        }
+    }
+
+    // checks if the contract can start paused
+    #[test]
+    fn start_paused() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            start_paused: true,
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // splits are rejected until the owner unpauses
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            split_msg.clone(),
+        );
+        match res.unwrap_err() {
+            ContractError::Paused { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info,
+            ExecuteMsg::SetPaused { paused: false },
+        )
+        .unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+    }
+
+    // checks if withdrawals can be frozen while splits continue
+    #[test]
+    fn withdrawals_frozen() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // only the owner can freeze withdrawals
+        let freeze_msg = ExecuteMsg::SetWithdrawalsFrozen { frozen: true };
+        let user_info = mock_info("person1", &[]);
+        let res = execute(deps.as_mut(), mock_env(), user_info, freeze_msg.clone());
+        match res.unwrap_err() {
+            ContractError::NotOwner {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let creator_info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), creator_info.clone(), freeze_msg).unwrap();
+
+        // sender can still send 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // but neither the recipients nor the owner can withdraw
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let user_info = mock_info("person1", &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            user_info.clone(),
+            withdraw_msg.clone(),
+        );
+        match res.unwrap_err() {
+            ContractError::WithdrawalsFrozen {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let withdraw_fees_msg = ExecuteMsg::WithdrawFees {
+            amount: None,
+            denom: None,
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            withdraw_fees_msg,
+        );
+        match res.unwrap_err() {
+            ContractError::WithdrawalsFrozen {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // once unfrozen the balance can be withdrawn
+        let unfreeze_msg = ExecuteMsg::SetWithdrawalsFrozen { frozen: false };
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, unfreeze_msg).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), user_info, withdraw_msg).unwrap();
+    }
+
+    // checks if splits stop for good after finalizing while withdrawals continue
+    #[test]
+    fn finalize() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            split_msg.clone(),
+        )
+        .unwrap();
+
+        // only the owner can finalize, and only once
+        let user_info = mock_info("person1", &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            user_info.clone(),
+            ExecuteMsg::Finalize {},
+        );
+        match res.unwrap_err() {
+            ContractError::NotOwner { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            ExecuteMsg::Finalize {},
+        )
+        .unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            ExecuteMsg::Finalize {},
+        );
+        match res.unwrap_err() {
+            ContractError::Finalized { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // new splits fail, even after unpausing
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            ExecuteMsg::SetPaused { paused: false },
+        )
+        .unwrap();
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg);
+        match res.unwrap_err() {
+            ContractError::Finalized { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // existing balances and fees can still be withdrawn
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user_info, withdraw_msg).unwrap();
+        let sub_msg = res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "person1".into(),
+                amount: coins(99, "usei"),
+            })
+        );
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info,
+            ExecuteMsg::WithdrawFees {
+                amount: None,
+                denom: None,
+            },
+        )
+        .unwrap();
+        let sub_msg = res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "creator".into(),
+                amount: coins(2, "usei"),
+            })
+        );
+    }
+
+    // checks if migrate only upgrades from a valid stored version
+    #[test]
+    fn migrate_version() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // migrating to the same version is allowed
+        let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+        // a newer stored version cannot be downgraded
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "99.0.0").unwrap();
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {});
+        match res.unwrap_err() {
+            ContractError::CannotDowngrade { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // a malformed stored version is rejected instead of compared
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "v1").unwrap();
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {});
+        match res.unwrap_err() {
+            ContractError::InvalidVersion { version } => assert_eq!("v1", version),
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // so is another contract
+        set_contract_version(deps.as_mut().storage, "crates.io:other", "0.1.0").unwrap();
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {});
+        match res.unwrap_err() {
+            ContractError::WrongContract { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // the first release stored no config and is not migrated
+        let mut deps = mock_dependencies();
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.1.0").unwrap();
+        let state = State {
+            owner: Addr::unchecked("creator"),
+        };
+        STATE.save(deps.as_mut().storage, &state).unwrap();
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {});
+        match res.unwrap_err() {
+            ContractError::UnsupportedLayout {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if the ownership can be transferred in two steps
+    #[test]
+    fn pending_owner() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // nobody is pending at first
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::PendingOwner {}).unwrap();
+        let pending: Option<Addr> = from_binary(&res).unwrap();
+        assert_eq!(None, pending);
+
+        // the owner proposes a new owner, then cancels the proposal
+        let propose_msg = ExecuteMsg::ProposeOwner {
+            owner: Addr::unchecked("multisig"),
+        };
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            propose_msg.clone(),
+        )
+        .unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::PendingOwner {}).unwrap();
+        let pending: Option<Addr> = from_binary(&res).unwrap();
+        assert_eq!(Some(Addr::unchecked("multisig")), pending);
+
+        let cancel_msg = ExecuteMsg::CancelOwnershipProposal {};
+        let _res = execute(deps.as_mut(), mock_env(), creator_info.clone(), cancel_msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::PendingOwner {}).unwrap();
+        let pending: Option<Addr> = from_binary(&res).unwrap();
+        assert_eq!(None, pending);
+
+        // a cancelled proposal cannot be accepted
+        let multisig_info = mock_info("multisig", &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            multisig_info.clone(),
+            ExecuteMsg::AcceptOwnership {},
+        );
+        match res.unwrap_err() {
+            ContractError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // the proposed owner accepts a new proposal and takes over
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, propose_msg).unwrap();
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            multisig_info,
+            ExecuteMsg::AcceptOwnership {},
+        )
+        .unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::PendingOwner {}).unwrap();
+        let pending: Option<Addr> = from_binary(&res).unwrap();
+        assert_eq!(None, pending);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::OwnerQuery {}).unwrap();
+        let value: OwnerResponse = from_binary(&res).unwrap();
+        assert_eq!(Addr::unchecked("multisig"), value.owner);
+    }
+
+    // checks if the fee above the threshold is forwarded to the fee recipient
+    #[test]
+    fn fee_auto_forward() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            fee_recipient: Some(Addr::unchecked("treasury")),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // only the owner can set the threshold
+        let threshold_msg = ExecuteMsg::SetFeeAutoForward {
+            threshold: Some(Uint128::new(15)),
+        };
+        let user_info = mock_info("person1", &[]);
+        let res = execute(deps.as_mut(), mock_env(), user_info, threshold_msg.clone());
+        match res.unwrap_err() {
+            ContractError::NotOwner { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            threshold_msg,
+        )
+        .unwrap();
+
+        // the first split collects 10 coins of fees, below the threshold
+        let sender_info = mock_info("sender", &coins(1000, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            split_msg.clone(),
+        )
+        .unwrap();
+        assert!(res.messages.is_empty());
+
+        // the second split brings the pot to 20 coins, the 5 above the threshold are forwarded
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        let sub_msg = res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "treasury".into(),
+                amount: coins(5, "usei"),
+            })
+        );
+        assert_eq!(
+            Uint128::new(15),
+            FEE_BY_DENOM
+                .load(deps.as_ref().storage, "usei".to_string())
+                .unwrap()
+        );
+    }
+
+    // checks if partial withdrawals below the minimum are rejected
+    #[test]
+    fn min_withdrawal() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // only the owner can set the minimum
+        let min_msg = ExecuteMsg::SetMinWithdrawal {
+            min: Uint128::new(50),
+        };
+        let user_info = mock_info("person1", &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            user_info.clone(),
+            min_msg.clone(),
+        );
+        match res.unwrap_err() {
+            ContractError::NotOwner { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, min_msg).unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // a partial withdrawal below the minimum is rejected
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: Some(Uint128::new(49)),
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user_info.clone(), withdraw_msg);
+        match res.unwrap_err() {
+            ContractError::WithdrawalTooSmall { min } => assert_eq!(Uint128::new(50), min),
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // person1 withdraws 60, then the remaining 39 in full
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: Some(Uint128::new(60)),
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user_info.clone(), withdraw_msg).unwrap();
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user_info, withdraw_msg).unwrap();
+        let sub_msg = res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "person1".into(),
+                amount: coins(39, "usei"),
+            })
+        );
+    }
+
+    // checks if a split cannot deposit more than the maximum
+    #[test]
+    fn max_deposit() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // only the owner can set the maximum
+        let max_msg = ExecuteMsg::SetMaxDeposit {
+            max: Some(Uint128::new(1000)),
+        };
+        let user_info = mock_info("person1", &[]);
+        let res = execute(deps.as_mut(), mock_env(), user_info, max_msg.clone());
+        match res.unwrap_err() {
+            ContractError::NotOwner { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, max_msg).unwrap();
+
+        // a deposit at the maximum is split
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let sender_info = mock_info("sender", &coins(1000, "usei"));
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg.clone()).unwrap();
+
+        // a deposit just above it is rejected
+        let sender_info = mock_info("sender", &coins(1001, "usei"));
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg);
+        match res.unwrap_err() {
+            ContractError::DepositTooLarge { max } => assert_eq!(Uint128::new(1000), max),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if a balance can be split again without leaving the contract
+    #[test]
+    fn withdraw_and_split() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 2000 coins to person1 (990) and person2 (990)
+        let sender_info = mock_info("sender", &coins(2000, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // person1 splits their 990 coins to person3 and person4, paying a fee of 10
+        let user_info = mock_info("person1", &[]);
+        let resplit_msg = ExecuteMsg::WithdrawAndSplit {
+            recipient1: Addr::unchecked("person3"),
+            recipient2: Addr::unchecked("person4"),
+        };
+
+        // the balance is held to the same checks as a withdrawal
+        let creator_info = mock_info("creator", &[]);
+        let checks = vec![
+            (
+                ExecuteMsg::SetWithdrawalsFrozen { frozen: true },
+                ExecuteMsg::SetWithdrawalsFrozen { frozen: false },
+            ),
+            (
+                ExecuteMsg::SetWithdrawLimit {
+                    limit: Some(Uint128::new(500)),
+                    window: 100,
+                },
+                ExecuteMsg::SetWithdrawLimit {
+                    limit: None,
+                    window: 100,
+                },
+            ),
+        ];
+        for (set_msg, unset_msg) in checks {
+            let _res = execute(deps.as_mut(), mock_env(), creator_info.clone(), set_msg).unwrap();
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                user_info.clone(),
+                resplit_msg.clone(),
+            );
+            match res.unwrap_err() {
+                ContractError::WithdrawalsFrozen {} | ContractError::WithdrawLimitExceeded {} => {}
+                e => panic!("unexpected error: {:?}", e),
+            }
+            let _res = execute(deps.as_mut(), mock_env(), creator_info.clone(), unset_msg).unwrap();
+        }
+
+        let res = execute(deps.as_mut(), mock_env(), user_info, resplit_msg).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(
+            mock_env().block.height,
+            LAST_WITHDRAW_BLOCK
+                .load(deps.as_ref().storage, Addr::unchecked("person1"))
+                .unwrap()
+        );
+
+        for (address, expected) in [("person1", 0), ("person3", 490), ("person4", 490)] {
+            let balance = AMOUNTS
+                .may_load(
+                    deps.as_ref().storage,
+                    (Addr::unchecked(address), "usei".to_string()),
+                )
+                .unwrap()
+                .unwrap_or_default();
+            assert_eq!(Uint128::new(expected), balance);
+        }
+        assert_eq!(
+            Uint128::new(30),
+            FEE_BY_DENOM
+                .load(deps.as_ref().storage, "usei".to_string())
+                .unwrap()
+        );
+    }
+
+    // checks if a donation goes to the fee pot
+    #[test]
+    fn donate() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins, leaving 2 coins of fees, then donates 100
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        let donor_info = mock_info("donor", &coins(100, "usei"));
+        let res = execute(deps.as_mut(), mock_env(), donor_info, ExecuteMsg::Donate {}).unwrap();
+        assert!(res.attributes.contains(&attr("donation", "100usei")));
+        assert_eq!(
+            Uint128::new(102),
+            FEE_BY_DENOM
+                .load(deps.as_ref().storage, "usei".to_string())
+                .unwrap()
+        );
+
+        // a donation of a denom that is not allowed is rejected
+        let donor_info = mock_info("donor", &coins(100, "uatom"));
+        let res = execute(deps.as_mut(), mock_env(), donor_info, ExecuteMsg::Donate {});
+        match res.unwrap_err() {
+            ContractError::WrongFundCoin { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if exact payouts are credited only when they match the amount sent
+    #[test]
+    fn split_exact() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // 1000 coins leave 990 after the fee, the payouts only cover 980
+        let sender_info = mock_info("sender", &coins(1000, "usei"));
+        let exact_msg = ExecuteMsg::SplitExact {
+            payouts: vec![
+                ("person1".to_string(), Uint128::new(700)),
+                ("person2".to_string(), Uint128::new(280)),
+            ],
+            on_invalid: InvalidPolicy::Reject,
+        };
+        let res = execute(deps.as_mut(), mock_env(), sender_info.clone(), exact_msg);
+        match res.unwrap_err() {
+            ContractError::AmountMismatch { expected, got } => {
+                assert_eq!(Uint128::new(1000), expected);
+                assert_eq!(Uint128::new(990), got);
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // payouts covering the 990 coins are credited as given
+        let exact_msg = ExecuteMsg::SplitExact {
+            payouts: vec![
+                ("person1".to_string(), Uint128::new(700)),
+                ("person2".to_string(), Uint128::new(290)),
+            ],
+            on_invalid: InvalidPolicy::Reject,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, exact_msg).unwrap();
+        assert_eq!(
+            DepositRecord {
+                sender: Addr::unchecked("sender"),
+                recipients: vec![Addr::unchecked("person1"), Addr::unchecked("person2")],
+                amount: coins(1000, "usei"),
+                fee: coins(10, "usei"),
+                height: mock_env().block.height,
+                time: mock_env().block.time,
+            },
+            HISTORY.load(deps.as_ref().storage, 1).unwrap()
+        );
+        for (address, expected) in [("person1", 700), ("person2", 290)] {
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::WithdrawableAmount {
+                    address: Addr::unchecked(address),
+                    denom: None,
+                },
+            )
+            .unwrap();
+            let value: Uint128 = from_binary(&res).unwrap();
+            assert_eq!(Uint128::new(expected), value);
+        }
+        assert_eq!(
+            Uint128::new(10),
+            FEE_BY_DENOM
+                .load(deps.as_ref().storage, "usei".to_string())
+                .unwrap()
+        );
+    }
+
+    // checks if the owner can sweep balances left after their expiry
+    #[test]
+    fn sweep_expired() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // the expiry must be in the future
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let env = mock_env();
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: Some(env.block.time),
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), sender_info.clone(), split_msg);
+        match res.unwrap_err() {
+            ContractError::InvalidExpiry { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // sender sends 200 coins to person1 (99) and person2 (99), expiring in 100 seconds
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: Some(env.block.time.plus_seconds(100)),
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), sender_info, split_msg).unwrap();
+
+        // nothing can be swept before the expiry
+        let sweep_msg = ExecuteMsg::SweepExpired {
+            recipient: Addr::unchecked("person1"),
+        };
+        let mut later_env = mock_env();
+        later_env.block.time = env.block.time.plus_seconds(100);
+        let res = execute(
+            deps.as_mut(),
+            later_env.clone(),
+            creator_info.clone(),
+            sweep_msg.clone(),
+        );
+        match res.unwrap_err() {
+            ContractError::NotExpired { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // only the owner can sweep
+        later_env.block.time = later_env.block.time.plus_seconds(1);
+        let user_info = mock_info("person2", &[]);
+        let res = execute(
+            deps.as_mut(),
+            later_env.clone(),
+            user_info,
+            sweep_msg.clone(),
+        );
+        match res.unwrap_err() {
+            ContractError::NotOwner { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // after the expiry the balance of person1 moves to the fees
+        let _res = execute(
+            deps.as_mut(),
+            later_env.clone(),
+            creator_info.clone(),
+            sweep_msg.clone(),
+        )
+        .unwrap();
+        assert!(!AMOUNTS.has(
+            deps.as_ref().storage,
+            (Addr::unchecked("person1"), "usei".to_string())
+        ));
+        assert_eq!(
+            Uint128::new(101),
+            FEE_BY_DENOM
+                .load(deps.as_ref().storage, "usei".to_string())
+                .unwrap()
+        );
+
+        // a split without an expiry neither expires nor clears the expiry of an earlier split
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: Some(later_env.block.time.plus_seconds(100)),
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let sender_info = mock_info("sender", &coins(4, "usei"));
+        let _res = execute(deps.as_mut(), later_env.clone(), sender_info, split_msg).unwrap();
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let _res = execute(deps.as_mut(), later_env.clone(), sender_info, split_msg).unwrap();
+
+        // only the 2 coins of the expiring split are swept, person1 keeps the other 99
+        later_env.block.time = later_env.block.time.plus_seconds(101);
+        let _res = execute(deps.as_mut(), later_env, creator_info, sweep_msg).unwrap();
+        assert_eq!(
+            Uint128::new(99),
+            AMOUNTS
+                .load(
+                    deps.as_ref().storage,
+                    (Addr::unchecked("person1"), "usei".to_string())
+                )
+                .unwrap()
+        );
+        assert_eq!(
+            Uint128::new(105),
+            FEE_BY_DENOM
+                .load(deps.as_ref().storage, "usei".to_string())
+                .unwrap()
+        );
+    }
+
+    // checks if the owner can bound the payouts of a single split
+    #[test]
+    fn max_recipients() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // the limit is between 1 and the hard cap
+        for max in [0, 51] {
+            let msg = ExecuteMsg::SetMaxRecipients { max };
+            let res = execute(deps.as_mut(), mock_env(), creator_info.clone(), msg);
+            match res.unwrap_err() {
+                ContractError::InvalidMaxRecipients { cap } => assert_eq!(50, cap),
+                e => panic!("unexpected error: {:?}", e),
+            }
+        }
+        let msg = ExecuteMsg::SetMaxRecipients { max: 2 };
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, msg).unwrap();
+
+        // person1 cannot withdraw their 99 coins to three destinations
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        let withdraw_msg = |destinations: Vec<&str>| ExecuteMsg::WithdrawSplit {
+            destinations: destinations
+                .into_iter()
+                .map(|destination| (Addr::unchecked(destination), 5_000))
+                .collect(),
+        };
+        let user_info = mock_info("person1", &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            user_info.clone(),
+            withdraw_msg(vec!["dest1", "dest2", "dest3"]),
+        );
+        match res.unwrap_err() {
+            ContractError::TooManyRecipients { max } => assert_eq!(2, max),
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            user_info,
+            withdraw_msg(vec!["dest1", "dest2"]),
+        )
+        .unwrap();
+
+        // three payouts exceed the limit of two
+        let exact_msg = ExecuteMsg::SplitExact {
+            payouts: vec![
+                ("person1".to_string(), Uint128::new(330)),
+                ("person2".to_string(), Uint128::new(330)),
+                ("person3".to_string(), Uint128::new(330)),
+            ],
+            on_invalid: InvalidPolicy::Reject,
+        };
+        let sender_info = mock_info("sender", &coins(1000, "usei"));
+        let res = execute(deps.as_mut(), mock_env(), sender_info.clone(), exact_msg);
+        match res.unwrap_err() {
+            ContractError::TooManyRecipients { max } => assert_eq!(2, max),
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // two payouts are within the limit
+        let exact_msg = ExecuteMsg::SplitExact {
+            payouts: vec![
+                ("person1".to_string(), Uint128::new(495)),
+                ("person2".to_string(), Uint128::new(495)),
+            ],
+            on_invalid: InvalidPolicy::Reject,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, exact_msg).unwrap();
+    }
+
+    // checks if a payout to an invalid address fails the split or is refunded to the sender
+    #[test]
+    fn split_exact_invalid_recipient() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // the address of the third payout is too short to be valid
+        let exact_msg = |on_invalid| ExecuteMsg::SplitExact {
+            payouts: vec![
+                ("person1".to_string(), Uint128::new(300)),
+                ("person2".to_string(), Uint128::new(300)),
+                ("x".to_string(), Uint128::new(390)),
+            ],
+            on_invalid,
+        };
+        let sender_info = mock_info("sender", &coins(1000, "usei"));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            exact_msg(InvalidPolicy::Reject),
+        );
+        match res.unwrap_err() {
+            ContractError::Std(_) => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        assert!(!AMOUNTS.has(
+            deps.as_ref().storage,
+            (Addr::unchecked("person1"), "usei".to_string())
+        ));
+
+        // the sender gets the third payout back, the others are credited as given
+        let execute_res = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info,
+            exact_msg(InvalidPolicy::RefundSender),
+        )
+        .unwrap();
+        assert_eq!(execute_res.attributes[1], attr("refunded", "390usei"));
+        for (address, expected) in [("person1", 300), ("person2", 300), ("sender", 390)] {
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::WithdrawableAmount {
+                    address: Addr::unchecked(address),
+                    denom: None,
+                },
+            )
+            .unwrap();
+            let value: Uint128 = from_binary(&res).unwrap();
+            assert_eq!(Uint128::new(expected), value);
+        }
+    }
+
+    // checks if a zero-amount coin sent along the single coin is ignored
+    #[test]
+    fn split_exact_zero_coin() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            allowed_denoms: Some(vec!["usei".to_string(), "uatom".to_string()]),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        let exact_msg = ExecuteMsg::SplitExact {
+            payouts: vec![
+                ("person1".to_string(), Uint128::new(700)),
+                ("person2".to_string(), Uint128::new(290)),
+            ],
+            on_invalid: InvalidPolicy::Reject,
+        };
+        let sender_info = mock_info("sender", &[coin(1000, "usei"), coin(0, "uatom")]);
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, exact_msg.clone()).unwrap();
+        assert_eq!(
+            Uint128::new(700),
+            AMOUNTS
+                .load(
+                    deps.as_ref().storage,
+                    (Addr::unchecked("person1"), "usei".to_string())
+                )
+                .unwrap()
+        );
+
+        // two coins with an amount are still rejected
+        let sender_info = mock_info("sender", &[coin(1000, "usei"), coin(1, "uatom")]);
+        let res = execute(deps.as_mut(), mock_env(), sender_info, exact_msg);
+        match res.unwrap_err() {
+            ContractError::WrongCoinSent { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if a zero-amount coin sent along a split is ignored
+    #[test]
+    fn split_zero_coin() {
+        let mut deps = mock_dependencies();
+
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+
+        // once while uatom is not allowed and once while it is
+        for i in 0..2 {
+            let sender_info = mock_info("sender", &[coin(200, "usei"), coin(0, "uatom")]);
+            let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg.clone()).unwrap();
+            assert_eq!(
+                Uint128::new(99 * (i + 1)),
+                AMOUNTS
+                    .load(
+                        deps.as_ref().storage,
+                        (Addr::unchecked("person1"), "usei".to_string())
+                    )
+                    .unwrap()
+            );
+
+            let msg = ExecuteMsg::AddDenom {
+                denom: "uatom".to_string(),
+            };
+            let _res = execute(deps.as_mut(), mock_env(), creator_info.clone(), msg).unwrap();
+        }
+    }
+
+    // checks if the sender keeps half of the split as their own balance
+    #[test]
+    fn split_keep_half() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins to person1 (99) and keeps 99
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::SplitKeepHalf {
+            recipient: Addr::unchecked("person1"),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        for person in ["person1", "sender"] {
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::WithdrawableAmount {
+                    address: Addr::unchecked(person),
+                    denom: None,
+                },
+            )
+            .unwrap();
+            let user_balance: Uint128 = from_binary(&res).unwrap();
+            assert_eq!(Uint128::new(99), user_balance);
+        }
+    }
+
+    // checks if the sender keeps their part and tips the rest
+    #[test]
+    fn split_tip() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // the tip cannot exceed the whole amount
+        let sender_info = mock_info("sender", &coins(1000, "usei"));
+        let tip_msg = ExecuteMsg::SplitTip {
+            tip_recipient: Addr::unchecked("person1"),
+            tip_bps: 10_001,
+        };
+        let res = execute(deps.as_mut(), mock_env(), sender_info.clone(), tip_msg);
+        match res.unwrap_err() {
+            ContractError::InvalidTipBps { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // sender tips 10% of the 990 coins left after the fee, the tip recipient is normalized
+        let tip_msg = ExecuteMsg::SplitTip {
+            tip_recipient: Addr::unchecked("PERSON1"),
+            tip_bps: 1000,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, tip_msg).unwrap();
+        for (address, expected) in [("sender", 891), ("person1", 99)] {
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::WithdrawableAmount {
+                    address: Addr::unchecked(address),
+                    denom: None,
+                },
+            )
+            .unwrap();
+            let value: Uint128 = from_binary(&res).unwrap();
+            assert_eq!(Uint128::new(expected), value);
+        }
+        assert_eq!(
+            Uint128::new(10),
+            FEE_BY_DENOM
+                .load(deps.as_ref().storage, "usei".to_string())
+                .unwrap()
+        );
+    }
+
+    // checks if no dust is lost on any split amount
+    #[test]
+    fn split_remainder_goes_to_fee() {
+        for gross in 100..300u128 {
+            let mut deps = mock_dependencies();
+
+            let instantiate_msg = InstantiateMsg::default();
+            let creator_info = mock_info("creator", &[]);
+            let _res =
+                instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+            let sender_info = mock_info("sender", &coins(gross, "usei"));
+            let split_msg = ExecuteMsg::Split {
+                recipient1: Addr::unchecked("person1"),
+                recipient2: Addr::unchecked("person2"),
+                memo: None,
+                idempotency_key: None,
+                vesting: None,
+                expires_at: None,
+                fee_on_top: false,
+                coupon: None,
+                nonce: None,
+                lock_until: None,
+                referrer: None,
+            };
+            let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+            let fee = FEE_BY_DENOM
+                .load(deps.as_ref().storage, "usei".to_string())
+                .unwrap();
+            let mut total = fee;
+            for person in ["person1", "person2"] {
+                let res = query(
+                    deps.as_ref(),
+                    mock_env(),
+                    QueryMsg::WithdrawableAmount {
+                        address: Addr::unchecked(person),
+                        denom: None,
+                    },
+                )
+                .unwrap();
+                let user_balance: Uint128 = from_binary(&res).unwrap();
+                total += user_balance;
+            }
+            assert_eq!(Uint128::new(gross), total);
+        }
+    }
+
+    // checks if the withdraw simulation matches the actual withdrawal
+    #[test]
+    fn simulate_withdraw() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // a withdrawal below the minimum is rejected by the simulation too
+        let min_msg = ExecuteMsg::SetMinWithdrawal {
+            min: Uint128::new(50),
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, min_msg).unwrap();
+        let query_msg = QueryMsg::SimulateWithdraw {
+            address: Addr::unchecked("person1"),
+            quantity: Some(Uint128::new(49)),
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg);
+        assert!(res.is_err());
+
+        // the simulation matches the withdrawal that follows
+        let query_msg = QueryMsg::SimulateWithdraw {
+            address: Addr::unchecked("person1"),
+            quantity: Some(Uint128::new(60)),
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let simulated: WithdrawResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            WithdrawResponse {
+                withdrawn: coin(60, "usei"),
+                remaining: Uint128::new(39),
+            },
+            simulated
+        );
+
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: Some(Uint128::new(60)),
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let user_info = mock_info("person1", &[]);
+        let res = execute(deps.as_mut(), mock_env(), user_info, withdraw_msg).unwrap();
+        let data: WithdrawResponse = from_binary(&res.data.expect("no data")).unwrap();
+        assert_eq!(simulated, data);
+    }
+
+    // checks if the simulation matches the actual split
+    #[test]
+    fn simulate_split() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        let query_msg = QueryMsg::SimulateSplit {
+            amount: Uint128::new(200),
+            recipients: 2,
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let simulation: SimulateSplitResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            SimulateSplitResponse {
+                fee: Uint128::new(2),
+                amount_each: Uint128::new(99),
+                remainder: Uint128::zero(),
+            },
+            simulation
+        );
+
+        // sender sends 200 coins to person1 and person2
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        let fee = FEE_BY_DENOM
+            .load(deps.as_ref().storage, "usei".to_string())
+            .unwrap();
+        assert_eq!(simulation.fee, fee);
+        for person in ["person1", "person2"] {
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::WithdrawableAmount {
+                    address: Addr::unchecked(person),
+                    denom: None,
+                },
+            )
+            .unwrap();
+            let user_balance: Uint128 = from_binary(&res).unwrap();
+            assert_eq!(simulation.amount_each, user_balance);
+        }
+
+        // the rounding remainder of an odd amount is reported
+        let query_msg = QueryMsg::SimulateSplit {
+            amount: Uint128::new(201),
+            recipients: 2,
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let simulation: SimulateSplitResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(3), simulation.fee);
+        assert_eq!(Uint128::new(99), simulation.amount_each);
+        assert_eq!(Uint128::new(1), simulation.remainder);
+
+        // the fee rate of the denom applies, raised to the minimum fee
+        let denom_fee_msg = ExecuteMsg::SetDenomFee {
+            denom: "usei".to_string(),
+            fee_bps: Some(500),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, denom_fee_msg).unwrap();
+        let query_msg = QueryMsg::SimulateSplit {
+            amount: Uint128::new(1000),
+            recipients: 3,
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg.clone()).unwrap();
+        let simulation: SimulateSplitResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(52), simulation.fee);
+        assert_eq!(Uint128::new(316), simulation.amount_each);
+        MIN_FEE
+            .save(deps.as_mut().storage, &Uint128::new(60))
+            .unwrap();
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let simulation: SimulateSplitResponse = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(61), simulation.fee);
+        assert_eq!(Uint128::new(313), simulation.amount_each);
+    }
+
+    // checks if the simulation leaves the rounding remainder out of the fee while the dust is tracked
+    #[test]
+    fn simulate_split_dust() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            track_dust: true,
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        let query_msg = QueryMsg::SimulateSplit {
+            amount: Uint128::new(201),
+            recipients: 2,
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let simulation: SimulateSplitResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            SimulateSplitResponse {
+                fee: Uint128::new(2),
+                amount_each: Uint128::new(99),
+                remainder: Uint128::new(1),
+            },
+            simulation
+        );
+
+        // sender sends 201 coins, the remainder goes to the dust
+        let sender_info = mock_info("sender", &coins(201, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        assert_eq!(
+            simulation.fee,
+            FEE_BY_DENOM
+                .load(deps.as_ref().storage, "usei".to_string())
+                .unwrap()
+        );
+        assert_eq!(
+            simulation.remainder,
+            DUST.load(deps.as_ref().storage, "usei".to_string())
+                .unwrap()
+        );
+    }
+
+    // checks if the running fee total is emitted and guarded against overflow
+    #[test]
+    fn split_total_fee() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender splits 200 coins twice, collecting a fee of 2 each time
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            split_msg.clone(),
+        )
+        .unwrap();
+        assert!(res.attributes.contains(&attr("total_fee", "2usei")));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            split_msg.clone(),
+        )
+        .unwrap();
+        assert!(res.attributes.contains(&attr("total_fee", "4usei")));
+
+        // a fee total that would overflow is rejected
+        FEE_BY_DENOM
+            .save(
+                deps.as_mut().storage,
+                "usei".to_string(),
+                &Uint128::new(u128::MAX - 1),
+            )
+            .unwrap();
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg);
+        match res.unwrap_err() {
+            ContractError::FeeOverflow { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if the fee is rounded as configured
+    #[test]
+    fn split_fee_rounding() {
+        // 1% of 250 is 2.5, flooring keeps the even 248 split while rounding up
+        // to 3 leaves 247 whose remainder is swept into the fee as well
+        for (fee_rounding, expected_fee, expected_share) in [
+            (None, 2, 124),
+            (Some(RoundingMode::Floor), 2, 124),
+            (Some(RoundingMode::Ceil), 4, 123),
+        ] {
+            let mut deps = mock_dependencies();
+
+            let instantiate_msg = InstantiateMsg {
+                fee_rounding,
+                ..InstantiateMsg::default()
+            };
+            let creator_info = mock_info("creator", &[]);
+            let _res =
+                instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+            let sender_info = mock_info("sender", &coins(250, "usei"));
+            let split_msg = ExecuteMsg::Split {
+                recipient1: Addr::unchecked("person1"),
+                recipient2: Addr::unchecked("person2"),
+                memo: None,
+                idempotency_key: None,
+                vesting: None,
+                expires_at: None,
+                fee_on_top: false,
+                coupon: None,
+                nonce: None,
+                lock_until: None,
+                referrer: None,
+            };
+            let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+            let fee = FEE_BY_DENOM
+                .load(deps.as_ref().storage, "usei".to_string())
+                .unwrap();
+            assert_eq!(Uint128::new(expected_fee), fee);
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::WithdrawableAmount {
+                    address: Addr::unchecked("person1"),
+                    denom: None,
+                },
+            )
+            .unwrap();
+            let user_balance: Uint128 = from_binary(&res).unwrap();
+            assert_eq!(Uint128::new(expected_share), user_balance);
+        }
+
+        // an exact fee is not rounded up
+        let mut deps = mock_dependencies();
+        let instantiate_msg = InstantiateMsg {
+            fee_rounding: Some(RoundingMode::Ceil),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        let fee = FEE_BY_DENOM
+            .load(deps.as_ref().storage, "usei".to_string())
+            .unwrap();
+        assert_eq!(Uint128::new(2), fee);
+    }
+
+    // checks if an amount that leaves a recipient with nothing is rejected
+    #[test]
+    fn split_amount_too_small() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            fee_rounding: Some(RoundingMode::Ceil),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // 2 coins leave 1 after the rounded up fee, which cannot be split into two
+        let sender_info = mock_info("sender", &coins(2, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg.clone());
+        match res.unwrap_err() {
+            ContractError::AmountTooSmall { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        assert!(!AMOUNTS.has(
+            deps.as_ref().storage,
+            (Addr::unchecked("person1"), "usei".to_string())
+        ));
+
+        // 3 coins leave 2, one for each recipient
+        let sender_info = mock_info("sender", &coins(3, "usei"));
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+    }
+
+    // checks if a sender with an override pays its own fee rate
+    #[test]
+    fn fee_override() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // only the owner can set an override, within 10000 basis points
+        let override_msg = ExecuteMsg::SetFeeOverride {
+            sender: Addr::unchecked("vip"),
+            fee_bps: 10_001,
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            override_msg,
+        );
+        match res.unwrap_err() {
+            ContractError::InvalidFeeBps { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let override_msg = ExecuteMsg::SetFeeOverride {
+            sender: Addr::unchecked("vip"),
+            fee_bps: 50,
+        };
+        let user_info = mock_info("vip", &[]);
+        let res = execute(deps.as_mut(), mock_env(), user_info, override_msg.clone());
+        match res.unwrap_err() {
+            ContractError::NotOwner { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            override_msg,
+        )
+        .unwrap();
+
+        // the vip pays 0.5% of 1000 coins, anyone else 1%
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let vip_info = mock_info("vip", &coins(1000, "usei"));
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            vip_info.clone(),
+            split_msg.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            Uint128::new(6),
+            FEE_BY_DENOM
+                .load(deps.as_ref().storage, "usei".to_string())
+                .unwrap()
+        );
+        let sender_info = mock_info("sender", &coins(1000, "usei"));
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg.clone()).unwrap();
+        assert_eq!(
+            Uint128::new(16),
+            FEE_BY_DENOM
+                .load(deps.as_ref().storage, "usei".to_string())
+                .unwrap()
+        );
+
+        // once cleared the vip pays the default fee
+        let clear_msg = ExecuteMsg::ClearFeeOverride {
+            sender: Addr::unchecked("vip"),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, clear_msg).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), vip_info, split_msg).unwrap();
+        assert_eq!(
+            Uint128::new(26),
+            FEE_BY_DENOM
+                .load(deps.as_ref().storage, "usei".to_string())
+                .unwrap()
+        );
+    }
+
+    // checks if the fee can be taken from the amount sent or paid on top of it
+    #[test]
+    fn split_fee_on_top() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // the fee is taken from 1000 coins, person1 and person2 get 495 each
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let sender_info = mock_info("sender", &coins(1000, "usei"));
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // the fee is paid on top of 1000 coins, person3 and person4 get 500 each
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person3"),
+            recipient2: Addr::unchecked("person4"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: true,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let sender_info = mock_info("sender", &coins(1010, "usei"));
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg.clone()).unwrap();
+
+        for (address, expected) in [
+            ("person1", 495),
+            ("person2", 495),
+            ("person3", 500),
+            ("person4", 500),
+        ] {
+            let balance = AMOUNTS
+                .load(
+                    deps.as_ref().storage,
+                    (Addr::unchecked(address), "usei".to_string()),
+                )
+                .unwrap();
+            assert_eq!(Uint128::new(expected), balance);
+        }
+        assert_eq!(
+            Uint128::new(20),
+            FEE_BY_DENOM
+                .load(deps.as_ref().storage, "usei".to_string())
+                .unwrap()
+        );
+
+        // no split amount plus its fee adds up to 1110 coins, 1099 + 10 is the closest
+        let sender_info = mock_info("sender", &coins(1110, "usei"));
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg);
+        match res.unwrap_err() {
+            ContractError::InvalidFeeOnTop { closest } => {
+                assert_eq!(Uint128::new(1109), closest)
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if a small split pays the minimum fee
+    #[test]
+    fn split_min_fee() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            min_fee: Some(Uint128::new(10)),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // 1% of 50 coins rounds down to nothing, the minimum fee of 10 applies
+        let sender_info = mock_info("sender", &coins(50, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg.clone()).unwrap();
+        assert_eq!(
+            Uint128::new(10),
+            FEE_BY_DENOM
+                .load(deps.as_ref().storage, "usei".to_string())
+                .unwrap()
+        );
+        let query_msg = QueryMsg::WithdrawableAmount {
+            address: Addr::unchecked("person1"),
+            denom: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let value: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(20), value);
+
+        // the minimum fee cannot take more than the amount split
+        let sender_info = mock_info("sender", &coins(11, "usei"));
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg);
+        match res.unwrap_err() {
+            ContractError::AmountTooSmall { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // the other split variants pay the minimum fee too
+        let sender_info = mock_info("sender", &coins(50, "usei"));
+        let split_msgs = vec![
+            ExecuteMsg::SplitWithPlatform {
+                recipient1: Addr::unchecked("person1"),
+                recipient2: Addr::unchecked("person2"),
+                platform: Addr::unchecked("platform"),
+                platform_bps: 0,
+            },
+            ExecuteMsg::SplitTip {
+                tip_recipient: Addr::unchecked("person1"),
+                tip_bps: 5_000,
+            },
+            ExecuteMsg::SplitExact {
+                payouts: vec![
+                    ("person1".to_string(), Uint128::new(20)),
+                    ("person2".to_string(), Uint128::new(20)),
+                ],
+                on_invalid: InvalidPolicy::Reject,
+            },
+        ];
+        for (i, split_msg) in split_msgs.into_iter().enumerate() {
+            let _res = execute(deps.as_mut(), mock_env(), sender_info.clone(), split_msg).unwrap();
+            assert_eq!(
+                Uint128::new(10 * (i as u128 + 2)),
+                FEE_BY_DENOM
+                    .load(deps.as_ref().storage, "usei".to_string())
+                    .unwrap()
+            );
+        }
+    }
+
+    // checks if the fee of a huge amount is computed without overflowing
+    #[test]
+    fn split_huge_amount() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // amount * fee_bps alone would not fit in a u128
+        let sender_info = mock_info("sender", &coins(u128::MAX / 2, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        for person in ["person1", "person2"] {
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::WithdrawableAmount {
+                    address: Addr::unchecked(person),
+                    denom: None,
+                },
+            )
+            .unwrap();
+            let user_balance: Uint128 = from_binary(&res).unwrap();
+            assert_eq!(
+                Uint128::new(84219885812932269707185215339362632335),
+                user_balance
+            );
+        }
+        let fee = FEE_BY_DENOM
+            .load(deps.as_ref().storage, "usei".to_string())
+            .unwrap();
+        assert_eq!(Uint128::new(1701411834604692317316873037158841057), fee);
+    }
+
+    // checks if a zero fee splits the full amount without recording a fee
+    #[test]
+    fn split_without_fee() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            fee_bps: Some(0),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins to person1 (100) and person2 (100)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        for person in ["person1", "person2"] {
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::WithdrawableAmount {
+                    address: Addr::unchecked(person),
+                    denom: None,
+                },
+            )
+            .unwrap();
+            let user_balance: Uint128 = from_binary(&res).unwrap();
+            assert_eq!(Uint128::new(100), user_balance);
+        }
+        let fee = FEE_BY_DENOM
+            .may_load(deps.as_ref().storage, "usei".to_string())
+            .unwrap();
+        assert_eq!(None, fee);
+
+        // an odd amount still sweeps the remainder into the fee
+        let sender_info = mock_info("sender", &coins(201, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        let fee = FEE_BY_DENOM
+            .load(deps.as_ref().storage, "usei".to_string())
+            .unwrap();
+        assert_eq!(Uint128::new(1), fee);
+    }
+
+    // checks if each denom pays its own fee rate
+    #[test]
+    fn split_denom_fee() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            allowed_denoms: Some(vec!["usei".to_string(), "uatom".to_string()]),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // only a denom that can be split can get a rate
+        let creator_info = mock_info("creator", &[]);
+        let denom_fee_msg = ExecuteMsg::SetDenomFee {
+            denom: "ujuno".to_string(),
+            fee_bps: Some(50),
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            denom_fee_msg,
+        );
+        match res.unwrap_err() {
+            ContractError::WrongFundCoin { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        for (denom, fee_bps) in [("usei", 50), ("uatom", 200)] {
+            let denom_fee_msg = ExecuteMsg::SetDenomFee {
+                denom: denom.to_string(),
+                fee_bps: Some(fee_bps),
+            };
+            let _res = execute(
+                deps.as_mut(),
+                mock_env(),
+                creator_info.clone(),
+                denom_fee_msg,
+            )
+            .unwrap();
+        }
+
+        // 10000 usei pay 50 and 10000 uatom pay 200
+        for denom in ["usei", "uatom"] {
+            let sender_info = mock_info("sender", &coins(10000, denom));
+            let split_msg = ExecuteMsg::Split {
+                recipient1: Addr::unchecked("person1"),
+                recipient2: Addr::unchecked("person2"),
+                memo: None,
+                idempotency_key: None,
+                vesting: None,
+                expires_at: None,
+                fee_on_top: false,
+                coupon: None,
+                nonce: None,
+                lock_until: None,
+                referrer: None,
+            };
+            let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        }
+        let fee = FEE_BY_DENOM
+            .load(deps.as_ref().storage, "usei".to_string())
+            .unwrap();
+        assert_eq!(Uint128::new(50), fee);
+        let fee = FEE_BY_DENOM
+            .load(deps.as_ref().storage, "uatom".to_string())
+            .unwrap();
+        assert_eq!(Uint128::new(200), fee);
+    }
+
+    // checks if a referrer is credited its cut of the fee
+    #[test]
+    fn split_referrer() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // a quarter of the fee goes to the referrer
+        let creator_info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::SetReferrerBps { referrer_bps: 2500 };
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, msg).unwrap();
+
+        // the fee of 1000 coins is 10, 2 for the referrer and 8 collected
+        let split_msg = |referrer: &str| ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: Some(Addr::unchecked(referrer)),
+        };
+        let sender_info = mock_info("sender", &coins(1000, "usei"));
+        let execute_res = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            split_msg("integrator"),
+        )
+        .unwrap();
+        assert!(execute_res.attributes.contains(&attr("referral", "2usei")));
+        for (address, expected) in [("person1", 495), ("person2", 495), ("integrator", 2)] {
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::WithdrawableAmount {
+                    address: Addr::unchecked(address),
+                    denom: None,
+                },
+            )
+            .unwrap();
+            let value: Uint128 = from_binary(&res).unwrap();
+            assert_eq!(Uint128::new(expected), value);
+        }
+        assert_eq!(
+            Uint128::new(8),
+            FEE_BY_DENOM
+                .load(deps.as_ref().storage, "usei".to_string())
+                .unwrap()
+        );
+
+        // the referral counts towards the lifetime total of the referrer
+        let query_msg = QueryMsg::TotalEarned {
+            address: Addr::unchecked("integrator"),
+            denom: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let earned: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(2), earned);
+
+        // neither a recipient nor the contract can be the referrer
+        for referrer in ["person1", MOCK_CONTRACT_ADDR] {
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                sender_info.clone(),
+                split_msg(referrer),
+            );
+            match res.unwrap_err() {
+                ContractError::InvalidReferrer {} => {}
+                e => panic!("unexpected error: {:?}", e),
+            }
+        }
+    }
+
+    // checks if a coupon waives the fee of a single split
+    #[test]
+    fn split_with_coupon() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            min_fee: Some(Uint128::new(5)),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // only the owner can create a coupon
+        let coupon_msg = ExecuteMsg::CreateCoupon {
+            code: "PROMO".to_string(),
+            fee_bps: 0,
+        };
+        let user_info = mock_info("sender", &[]);
+        let res = execute(deps.as_mut(), mock_env(), user_info, coupon_msg.clone());
+        match res.unwrap_err() {
+            ContractError::NotOwner {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let creator_info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, coupon_msg).unwrap();
+
+        // sender sends 200 coins to person1 (100) and person2 (100) without any fee
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: Some("PROMO".to_string()),
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg.clone()).unwrap();
+        for person in ["person1", "person2"] {
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::WithdrawableAmount {
+                    address: Addr::unchecked(person),
+                    denom: None,
+                },
+            )
+            .unwrap();
+            let user_balance: Uint128 = from_binary(&res).unwrap();
+            assert_eq!(Uint128::new(100), user_balance);
+        }
+        let fee = FEE_BY_DENOM
+            .may_load(deps.as_ref().storage, "usei".to_string())
+            .unwrap();
+        assert_eq!(None, fee);
+
+        // the coupon cannot be used twice
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg);
+        match res.unwrap_err() {
+            ContractError::InvalidCoupon {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if the platform cut is carved out before splitting
+    #[test]
+    fn split_with_platform() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 1000 coins, 10 are fee and 50 go to the platform
+        let sender_info = mock_info("sender", &coins(1000, "usei"));
+        let split_msg = ExecuteMsg::SplitWithPlatform {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            platform: Addr::unchecked("platform"),
+            platform_bps: 500,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info.clone(), split_msg).unwrap();
+
+        for (address, expected) in [("person1", 470), ("person2", 470), ("platform", 50)] {
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::WithdrawableAmount {
+                    address: Addr::unchecked(address),
+                    denom: None,
+                },
+            )
+            .unwrap();
+            let user_balance: Uint128 = from_binary(&res).unwrap();
+            assert_eq!(Uint128::new(expected), user_balance);
+        }
+        let fee = FEE_BY_DENOM
+            .load(deps.as_ref().storage, "usei".to_string())
+            .unwrap();
+        assert_eq!(Uint128::new(10), fee);
+
+        // the fee and the platform cut cannot exceed the amount
+        let split_msg = ExecuteMsg::SplitWithPlatform {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            platform: Addr::unchecked("platform"),
+            platform_bps: 9_901,
+        };
+        let res = execute(deps.as_mut(), mock_env(), sender_info.clone(), split_msg);
+        match res.unwrap_err() {
+            ContractError::InvalidPlatformBps { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // an empty recipient is rejected
+        let split_msg = ExecuteMsg::SplitWithPlatform {
+            recipient1: Addr::unchecked(""),
+            recipient2: Addr::unchecked("person2"),
+            platform: Addr::unchecked("platform"),
+            platform_bps: 500,
+        };
+        let res = execute(deps.as_mut(), mock_env(), sender_info.clone(), split_msg);
+        match res.unwrap_err() {
+            ContractError::InvalidRecipient {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // the recipients are normalized and a zero cut credits nothing
+        let split_msg = ExecuteMsg::SplitWithPlatform {
+            recipient1: Addr::unchecked("PERSON1"),
+            recipient2: Addr::unchecked("person2"),
+            platform: Addr::unchecked("other_platform"),
+            platform_bps: 0,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        assert_eq!(
+            Uint128::new(965),
+            AMOUNTS
+                .load(
+                    deps.as_ref().storage,
+                    (Addr::unchecked("person1"), "usei".to_string())
+                )
+                .unwrap()
+        );
+        assert!(!AMOUNTS.has(
+            deps.as_ref().storage,
+            (Addr::unchecked("other_platform"), "usei".to_string())
+        ));
+    }
+
+    // checks if the contract itself cannot be a recipient
+    #[test]
+    fn split_to_contract() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked(MOCK_CONTRACT_ADDR),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg);
+        match res.unwrap_err() {
+            ContractError::RecipientIsContract { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if the old balance is updated for the same recipients
+    #[test]
+    fn update_old_balance() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins twice to person1 and person2
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+
+        let _res1 = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            split_msg.clone(),
+        )
+        .unwrap();
+        let _res2 = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            split_msg.clone(),
+        )
+        .unwrap();
+
+        // check the updated balance of person2
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::WithdrawableAmount {
+                address: Addr::unchecked("person2"),
+                denom: None,
+            },
+        )
+        .unwrap();
+        let user_balance: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(198), user_balance);
+    }
+
+    #[test]
+    fn withdraw() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // person1 withdraws 50 coins
+        let user_info = mock_info("person1", &[]);
+        let mut msg = ExecuteMsg::Withdraw {
+            quantity: Some(Uint128::new(50)),
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let execute_res = execute(deps.as_mut(), mock_env(), user_info.clone(), msg).unwrap();
+        assert_eq!(1, execute_res.messages.len());
+
+        let sub_msg = execute_res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "person1".into(),
+                amount: coins(50, "usei"),
+            })
+        );
+        assert_eq!(
+            execute_res.attributes,
+            vec![
+                attr("action", "withdraw"),
+                attr("to", "person1"),
+                attr("amount", "50"),
+                attr("balance", "49"),
+            ]
+        );
+        let data: WithdrawResponse = from_binary(&execute_res.data.expect("no data")).unwrap();
+        assert_eq!(
+            WithdrawResponse {
+                withdrawn: coin(50, "usei"),
+                remaining: Uint128::new(49),
+            },
+            data
+        );
+
+        // checks the balance of person1 after the partial withdraw
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::WithdrawableAmount {
+                address: Addr::unchecked("person1"),
+                denom: None,
+            },
+        )
+        .unwrap();
+        let user_balance: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(49), user_balance);
+
+        // person1 withdraws the entire balance (49)
+        msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let execute_res = execute(deps.as_mut(), mock_env(), user_info, msg).unwrap();
+        assert_eq!(1, execute_res.messages.len());
+
+        let sub_msg = execute_res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "person1".into(),
+                amount: coins(49, "usei"),
+            })
+        );
+        assert_eq!(
+            execute_res.attributes,
+            vec![
+                attr("action", "withdraw"),
+                attr("to", "person1"),
+                attr("amount", "49"),
+                attr("balance", "0"),
+            ]
+        );
+
+        // checks the balance of person1 after withdrawal
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::WithdrawableAmount {
+                address: Addr::unchecked("person1"),
+                denom: None,
+            },
+        )
+        .unwrap();
+        let user_balance: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::zero(), user_balance);
+    }
+
+    // checks if a failed withdrawal send restores the balance
+    #[test]
+    fn failed_withdraw_restores_balance() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // person1 withdraws 50 coins with a reply on a failed send
+        let user_info = mock_info("person1", &[]);
+        let msg = ExecuteMsg::Withdraw {
+            quantity: Some(Uint128::new(50)),
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let execute_res = execute(deps.as_mut(), mock_env(), user_info, msg).unwrap();
+        let sub_msg = execute_res.messages.first().expect("no message");
+        assert_eq!(WITHDRAW_REPLY_ID, sub_msg.id);
+
+        // the bank send fails
+        let reply_msg = Reply {
+            id: sub_msg.id,
+            result: SubMsgResult::Err("cannot receive funds".to_string()),
+        };
+        let _res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+        // checks the balance of person1 is restored
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::WithdrawableAmount {
+                address: Addr::unchecked("person1"),
+                denom: None,
+            },
+        )
+        .unwrap();
+        let user_balance: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(99), user_balance);
+
+        // the restore is in the audit log
+        let id = AUDIT_COUNT.load(deps.as_ref().storage).unwrap();
+        assert_eq!(
+            AuditEntry {
+                action: "restore_withdrawal".to_string(),
+                actor: Addr::unchecked("person1"),
+                amount: coins(50, "usei"),
+                height: mock_env().block.height,
+            },
+            AUDIT_LOG.load(deps.as_ref().storage, id).unwrap()
+        );
+    }
+
+    // checks if a failed fee withdrawal send restores the fee pot
+    #[test]
+    fn failed_withdraw_fees_restores_pot() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // sender sends 1000 coins, leaving 10 coins of fees
+        let sender_info = mock_info("sender", &coins(1000, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // the owner withdraws 4 coins of fees, then all of them, and both sends fail
+        for amount in [Some(Uint128::new(4)), None] {
+            let msg = ExecuteMsg::WithdrawFees {
+                amount,
+                denom: None,
+            };
+            let execute_res =
+                execute(deps.as_mut(), mock_env(), creator_info.clone(), msg).unwrap();
+            let sub_msg = execute_res.messages.first().expect("no message");
+            assert_eq!(WITHDRAW_FEES_REPLY_ID, sub_msg.id);
+
+            let reply_msg = Reply {
+                id: sub_msg.id,
+                result: SubMsgResult::Err("cannot receive funds".to_string()),
+            };
+            let _res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+            // checks the fee pot is restored
+            assert_eq!(
+                Uint128::new(10),
+                FEE_BY_DENOM
+                    .load(deps.as_ref().storage, "usei".to_string())
+                    .unwrap()
+            );
+        }
+    }
+
+    // checks if batched withdrawals are queued and sent together on flush
+    #[test]
+    fn flush_payouts() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            batch_payouts: true,
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // nothing is queued yet
+        let anyone_info = mock_info("anyone", &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            anyone_info.clone(),
+            ExecuteMsg::FlushPayouts { limit: None },
+        );
+        match res.unwrap_err() {
+            ContractError::NoPendingPayouts {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // both withdrawals are queued without sending anything
+        for person in ["person1", "person2"] {
+            let withdraw_msg = ExecuteMsg::Withdraw {
+                quantity: None,
+                denom: None,
+                percent: None,
+                from: None,
+                to: None,
+            };
+            let user_info = mock_info(person, &[]);
+            let res = execute(deps.as_mut(), mock_env(), user_info, withdraw_msg).unwrap();
+            assert_eq!(0, res.messages.len());
+            assert!(res.attributes.contains(&attr("action", "queue_payout")));
+        }
+
+        // anyone flushes the sends, oldest first and at most limit of them
+        for person in ["person1", "person2"] {
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                anyone_info.clone(),
+                ExecuteMsg::FlushPayouts { limit: Some(1) },
+            )
+            .unwrap();
+            assert_eq!(1, res.messages.len());
+            assert_eq!(
+                res.messages[0].msg,
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: person.into(),
+                    amount: coins(99, "usei"),
+                })
+            );
+        }
+        assert!(PENDING_PAYOUTS
+            .range(deps.as_ref().storage, None, None, Order::Ascending)
+            .next()
+            .is_none());
+    }
+
+    // checks if a flushed payout whose send fails is credited back to its recipient
+    #[test]
+    fn flush_payouts_failed_send() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            batch_payouts: true,
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99), who both withdraw
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        for person in ["person1", "person2"] {
+            let withdraw_msg = ExecuteMsg::Withdraw {
+                quantity: None,
+                denom: None,
+                percent: None,
+                from: None,
+                to: None,
+            };
+            let user_info = mock_info(person, &[]);
+            let _res = execute(deps.as_mut(), mock_env(), user_info, withdraw_msg).unwrap();
+        }
+
+        // the send to person1 fails, the send to person2 goes through
+        let anyone_info = mock_info("anyone", &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            anyone_info,
+            ExecuteMsg::FlushPayouts { limit: None },
+        )
+        .unwrap();
+        assert_eq!(2, res.messages.len());
+        let reply_msg = Reply {
+            id: res.messages[0].id,
+            result: SubMsgResult::Err("cannot receive funds".to_string()),
+        };
+        let _res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+        for (person, expected) in [("person1", 99), ("person2", 0)] {
+            let balance = AMOUNTS
+                .may_load(
+                    deps.as_ref().storage,
+                    (Addr::unchecked(person), "usei".to_string()),
+                )
+                .unwrap()
+                .unwrap_or_default();
+            assert_eq!(Uint128::new(expected), balance);
+        }
+    }
+
+    // checks if a queued withdrawal can be cancelled back into the balance
+    #[test]
+    fn cancel_pending_payout() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            batch_payouts: true,
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // person1 queues the whole balance, then cancels it
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let user_info = mock_info("person1", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), user_info.clone(), withdraw_msg).unwrap();
+        let query_msg = QueryMsg::WithdrawableAmount {
+            address: Addr::unchecked("person1"),
+            denom: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg.clone()).unwrap();
+        let user_balance: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::zero(), user_balance);
+
+        let cancel_msg = ExecuteMsg::CancelPendingPayout {};
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            user_info.clone(),
+            cancel_msg.clone(),
+        )
+        .unwrap();
+        let res = query(deps.as_ref(), mock_env(), query_msg.clone()).unwrap();
+        let user_balance: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(99), user_balance);
+
+        // cancelling again credits nothing more
+        let res = execute(deps.as_mut(), mock_env(), user_info, cancel_msg);
+        match res.unwrap_err() {
+            ContractError::NoPendingPayouts {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let user_balance: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(99), user_balance);
+        assert!(PENDING_PAYOUTS
+            .range(deps.as_ref().storage, None, None, Order::Ascending)
+            .next()
+            .is_none());
+    }
+
+    // checks if a balance can be withdrawn across several destinations
+    #[test]
+    fn withdraw_split() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 202 coins to person1 (100) and person2 (100)
+        let sender_info = mock_info("sender", &coins(202, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // the shares must cover the whole balance
+        let user_info = mock_info("person1", &[]);
+        let withdraw_msg = ExecuteMsg::WithdrawSplit {
+            destinations: vec![
+                (Addr::unchecked("wallet1"), 6_000),
+                (Addr::unchecked("wallet2"), 3_000),
+            ],
+        };
+        let res = execute(deps.as_mut(), mock_env(), user_info.clone(), withdraw_msg);
+        match res.unwrap_err() {
+            ContractError::InvalidShares {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // person1 drains 60 to wallet1 and 40 to wallet2
+        let withdraw_msg = ExecuteMsg::WithdrawSplit {
+            destinations: vec![
+                (Addr::unchecked("wallet1"), 6_000),
+                (Addr::unchecked("wallet2"), 4_000),
+            ],
+        };
+        let res = execute(deps.as_mut(), mock_env(), user_info, withdraw_msg).unwrap();
+        assert_eq!(2, res.messages.len());
+        for (sub_msg, (wallet, amount)) in
+            res.messages.iter().zip([("wallet1", 60), ("wallet2", 40)])
+        {
+            assert_eq!(
+                sub_msg.msg,
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: wallet.into(),
+                    amount: coins(amount, "usei"),
+                })
+            );
+        }
+        let balance = AMOUNTS
+            .may_load(
+                deps.as_ref().storage,
+                (Addr::unchecked("person1"), "usei".to_string()),
+            )
+            .unwrap();
+        assert_eq!(None, balance);
+    }
+
+    // checks if every denom is withdrawn in a single send
+    #[test]
+    fn withdraw_all() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            allowed_denoms: Some(vec!["usei".to_string(), "uatom".to_string()]),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // send 200 usei and 1000 uatom from the sender
+        let sender_info = mock_info("sender", &[coin(200, "usei"), coin(1000, "uatom")]);
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // person1 withdraws both denoms at once
+        let user_info = mock_info("person1", &[]);
+        let execute_res = execute(
+            deps.as_mut(),
+            mock_env(),
+            user_info.clone(),
+            ExecuteMsg::WithdrawAll {},
+        )
+        .unwrap();
+        assert_eq!(1, execute_res.messages.len());
+        let sub_msg = execute_res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "person1".into(),
+                amount: vec![coin(495, "uatom"), coin(99, "usei")],
+            })
+        );
+
+        // nothing is left to withdraw
+        let execute_res = execute(
+            deps.as_mut(),
+            mock_env(),
+            user_info,
+            ExecuteMsg::WithdrawAll {},
+        );
+        match execute_res.unwrap_err() {
+            ContractError::NothingToWithdraw { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if only the listed denoms are withdrawn
+    #[test]
+    fn withdraw_denoms() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            allowed_denoms: Some(vec![
+                "usei".to_string(),
+                "uatom".to_string(),
+                "uosmo".to_string(),
+            ]),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // send 200 usei, 1000 uatom and 400 uosmo from the sender
+        let sender_info = mock_info(
+            "sender",
+            &[coin(200, "usei"), coin(1000, "uatom"), coin(400, "uosmo")],
+        );
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // a denom person1 does not hold is rejected
+        let user_info = mock_info("person1", &[]);
+        let withdraw_msg = ExecuteMsg::WithdrawDenoms {
+            denoms: vec!["usei".to_string(), "ujuno".to_string()],
+        };
+        let execute_res = execute(deps.as_mut(), mock_env(), user_info.clone(), withdraw_msg);
+        match execute_res.unwrap_err() {
+            ContractError::DenomNotHeld { denom } => assert_eq!("ujuno", denom),
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // person1 withdraws usei and uosmo in a single message
+        let withdraw_msg = ExecuteMsg::WithdrawDenoms {
+            denoms: vec!["usei".to_string(), "uosmo".to_string()],
+        };
+        let execute_res = execute(deps.as_mut(), mock_env(), user_info, withdraw_msg).unwrap();
+        assert_eq!(1, execute_res.messages.len());
+        let sub_msg = execute_res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "person1".into(),
+                amount: vec![coin(198, "uosmo"), coin(99, "usei")],
+            })
+        );
+
+        // uatom stays credited
+        for (denom, expected) in [("uatom", 495), ("usei", 0), ("uosmo", 0)] {
+            let query_msg = QueryMsg::WithdrawableAmount {
+                address: Addr::unchecked("person1"),
+                denom: Some(denom.to_string()),
+            };
+            let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+            let value: Uint128 = from_binary(&res).unwrap();
+            assert_eq!(Uint128::new(expected), value);
+        }
+    }
+
+    // checks if the vesting schedule shows the unlocked part growing over time
+    #[test]
+    fn vesting_info() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        let vesting_info = |deps: Deps, seconds: u64| -> Option<VestingInfoResponse> {
+            let mut env = mock_env();
+            env.block.time = env.block.time.plus_seconds(seconds);
+            let query_msg = QueryMsg::VestingInfo {
+                address: Addr::unchecked("person1"),
+                denom: None,
+            };
+            from_binary(&query(deps, env, query_msg).unwrap()).unwrap()
+        };
+        assert_eq!(None, vesting_info(deps.as_ref(), 0));
+
+        // half of the 990 coins of person1 unlock over 1000 seconds
+        let sender_info = mock_info("sender", &coins(2000, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: Some(VestingTerms {
+                immediate_bps: 5_000,
+                cliff: 0,
+                duration: 1_000,
+            }),
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        let start = mock_env().block.time.seconds();
+        assert_eq!(
+            Some(VestingInfoResponse {
+                total: Uint128::new(495),
+                unlocked: Uint128::new(123),
+                locked: Uint128::new(372),
+                start,
+                duration: 1_000,
+            }),
+            vesting_info(deps.as_ref(), 250)
+        );
+        assert_eq!(
+            Some(VestingInfoResponse {
+                total: Uint128::new(495),
+                unlocked: Uint128::new(247),
+                locked: Uint128::new(248),
+                start,
+                duration: 1_000,
+            }),
+            vesting_info(deps.as_ref(), 500)
+        );
+    }
+
+    // checks if a vesting share unlocks linearly after the cliff
+    #[test]
+    fn split_vesting() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 2000 coins to person1 (990) and person2 (990),
+        // half of each share unlocks over 1000 seconds after a 100 second cliff
+        let sender_info = mock_info("sender", &coins(2000, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: Some(VestingTerms {
+                immediate_bps: 5_000,
+                cliff: 100,
+                duration: 1_000,
+            }),
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            split_msg.clone(),
+        )
+        .unwrap();
+
+        let withdrawable = |deps: Deps, seconds: u64| -> Uint128 {
+            let mut env = mock_env();
+            env.block.time = env.block.time.plus_seconds(seconds);
+            let query_msg = QueryMsg::WithdrawableAmount {
+                address: Addr::unchecked("person1"),
+                denom: None,
+            };
+            from_binary(&query(deps, env, query_msg).unwrap()).unwrap()
+        };
+
+        // at the start and before the cliff only the immediate half is withdrawable
+        assert_eq!(Uint128::new(495), withdrawable(deps.as_ref(), 0));
+        assert_eq!(Uint128::new(495), withdrawable(deps.as_ref(), 99));
+        // halfway 248 of the 495 are still locked
+        assert_eq!(Uint128::new(742), withdrawable(deps.as_ref(), 500));
+        // fully vested
+        assert_eq!(Uint128::new(990), withdrawable(deps.as_ref(), 1_000));
+
+        // another vesting split is rejected while this one is active
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg);
+        match res.unwrap_err() {
+            ContractError::VestingActive { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // person1 cannot withdraw more than the unlocked amount
+        let user_info = mock_info("person1", &[]);
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: Some(Uint128::new(496)),
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user_info.clone(), withdraw_msg);
+        match res.unwrap_err() {
+            ContractError::ExceededQuantity { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // withdrawing everything at the start sends the unlocked half and keeps the rest
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user_info.clone(), withdraw_msg).unwrap();
+        let sub_msg = res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "person1".into(),
+                amount: coins(495, "usei"),
+            })
+        );
+        assert_eq!(Uint128::zero(), withdrawable(deps.as_ref(), 0));
+
+        // once fully vested the rest can be withdrawn
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(1_000);
+        let res = execute(deps.as_mut(), env, user_info, ExecuteMsg::WithdrawAll {}).unwrap();
+        let sub_msg = res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "person1".into(),
+                amount: coins(495, "usei"),
+            })
+        );
+    }
+
+    // checks if only the delegate of a recipient can withdraw on their behalf
+    #[test]
+    fn withdraw_delegate() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // the bot cannot withdraw before it is set as delegate
+        let bot_info = mock_info("bot", &[]);
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: Some(Uint128::new(40)),
+            denom: None,
+            percent: None,
+            from: Some(Addr::unchecked("person1")),
+            to: None,
+        };
+        let execute_res = execute(
+            deps.as_mut(),
+            mock_env(),
+            bot_info.clone(),
+            withdraw_msg.clone(),
+        );
+        match execute_res.unwrap_err() {
+            ContractError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // person1 sets the bot as delegate, which withdraws to person1
+        let delegate_msg = ExecuteMsg::SetWithdrawDelegate {
+            delegate: Some(Addr::unchecked("bot")),
+        };
+        let user_info = mock_info("person1", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), user_info, delegate_msg).unwrap();
+        let execute_res =
+            execute(deps.as_mut(), mock_env(), bot_info.clone(), withdraw_msg).unwrap();
+        let sub_msg = execute_res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "person1".into(),
+                amount: coins(40, "usei"),
+            })
+        );
+
+        // the bot can send the tokens elsewhere
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: Some(Uint128::new(50)),
+            denom: None,
+            percent: None,
+            from: Some(Addr::unchecked("person1")),
+            to: Some(Addr::unchecked("vault")),
+        };
+        let execute_res =
+            execute(deps.as_mut(), mock_env(), bot_info.clone(), withdraw_msg).unwrap();
+        let sub_msg = execute_res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "vault".into(),
+                amount: coins(50, "usei"),
+            })
+        );
+
+        // the bot is not a delegate of person2
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: Some(Addr::unchecked("person2")),
+            to: None,
+        };
+        let execute_res = execute(deps.as_mut(), mock_env(), bot_info, withdraw_msg);
+        match execute_res.unwrap_err() {
+            ContractError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // person1 has 9 coins left
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::WithdrawableAmount {
+                address: Addr::unchecked("person1"),
+                denom: None,
+            },
+        )
+        .unwrap();
+        let user_balance: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(9), user_balance);
+    }
+
+    // checks if withdrawing a zero balance is rejected without a bank send
+    #[test]
+    fn withdraw_zero_balance() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // person1 withdraws the whole balance as a quantity, leaving a zero balance
+        let user_info = mock_info("person1", &[]);
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: Some(Uint128::new(99)),
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user_info.clone(), withdraw_msg).unwrap();
+
+        // withdrawing the zero balance fails
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), user_info, withdraw_msg);
+        match res.unwrap_err() {
+            ContractError::NothingToWithdraw { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if the owner can remove balances left at zero
+    #[test]
+    fn prune_zero_balances() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // person1 withdraws the whole balance as a quantity, leaving a zero entry
+        let user_info = mock_info("person1", &[]);
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: Some(Uint128::new(99)),
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user_info, withdraw_msg).unwrap();
+        let key = (Addr::unchecked("person1"), "usei".to_string());
+        assert!(AMOUNTS.has(deps.as_ref().storage, key.clone()));
+
+        // only the owner can prune
+        let prune_msg = ExecuteMsg::PruneZeroBalances { limit: 10 };
+        let user_info = mock_info("person2", &[]);
+        let res = execute(deps.as_mut(), mock_env(), user_info, prune_msg.clone());
+        match res.unwrap_err() {
+            ContractError::NotOwner { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // the zero entry is removed and person2's balance is kept
+        let res = execute(deps.as_mut(), mock_env(), creator_info, prune_msg).unwrap();
+        let pruned: u32 = from_binary(&res.data.expect("no data")).unwrap();
+        assert_eq!(1, pruned);
+        assert!(!AMOUNTS.has(deps.as_ref().storage, key));
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Stats {}).unwrap();
+        let stats: StatsResponse = from_binary(&res).unwrap();
+        assert_eq!(1, stats.recipient_count);
+    }
+
+    // checks if funds attached to a withdrawal are rejected
+    #[test]
+    fn withdraw_with_funds() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // person1 attaches funds to a withdrawal
+        let person1_info = mock_info("person1", &coins(10, "usei"));
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let execute_res = execute(deps.as_mut(), mock_env(), person1_info, withdraw_msg);
+        match execute_res.unwrap_err() {
+            ContractError::UnexpectedFunds { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // the owner attaches funds to a fee withdrawal
+        let owner_info = mock_info("creator", &coins(10, "usei"));
+        let execute_res = execute(
+            deps.as_mut(),
+            mock_env(),
+            owner_info,
+            ExecuteMsg::WithdrawFees {
+                amount: None,
+                denom: None,
+            },
+        );
+        match execute_res.unwrap_err() {
+            ContractError::UnexpectedFunds { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // the balance is untouched
+        let query_msg = QueryMsg::WithdrawableAmount {
+            address: Addr::unchecked("person1"),
+            denom: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let value: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(99), value);
+    }
+
+    // checks if a percent of the balance can be withdrawn
+    #[test]
+    fn withdraw_percent() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 202 coins to person1 (100) and person2 (100)
+        let sender_info = mock_info("sender", &coins(202, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // person1 withdraws 50% of the balance
+        let user_info = mock_info("person1", &[]);
+        let msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: Some(50),
+            from: None,
+            to: None,
+        };
+        let execute_res = execute(deps.as_mut(), mock_env(), user_info.clone(), msg).unwrap();
+        let sub_msg = execute_res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "person1".into(),
+                amount: coins(50, "usei"),
+            })
+        );
+
+        // quantity and percent cannot be combined
+        let msg = ExecuteMsg::Withdraw {
+            quantity: Some(Uint128::new(10)),
+            denom: None,
+            percent: Some(50),
+            from: None,
+            to: None,
+        };
+        let execute_res = execute(deps.as_mut(), mock_env(), user_info, msg);
+        match execute_res.unwrap_err() {
+            ContractError::ConflictingWithdrawArgs { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if a recipient has to wait for the cooldown between two withdrawals
+    #[test]
+    fn withdraw_cooldown() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        let creator_info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::SetWithdrawCooldown { blocks: 10 };
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, msg).unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: Some(Uint128::new(40)),
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let user_info = mock_info("person1", &[]);
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            user_info.clone(),
+            withdraw_msg.clone(),
+        )
+        .unwrap();
+
+        // a second withdrawal within the cooldown is rejected, also of everything at once
+        let retry_at = mock_env().block.height + 10;
+        let mut env = mock_env();
+        env.block.height = retry_at - 1;
+        for msg in [withdraw_msg.clone(), ExecuteMsg::WithdrawAll {}] {
+            let res = execute(deps.as_mut(), env.clone(), user_info.clone(), msg);
+            match res.unwrap_err() {
+                ContractError::CooldownActive { retry_at: at } => assert_eq!(retry_at, at),
+                e => panic!("unexpected error: {:?}", e),
+            }
+        }
+
+        // the cooldown of one recipient does not hold back another
+        let other_info = mock_info("person2", &[]);
+        let _res = execute(deps.as_mut(), env.clone(), other_info, withdraw_msg.clone()).unwrap();
+
+        // once the cooldown has passed the recipient withdraws again
+        env.block.height = retry_at;
+        let _res = execute(deps.as_mut(), env, user_info, withdraw_msg).unwrap();
+        assert_eq!(
+            Uint128::new(19),
+            AMOUNTS
+                .load(
+                    deps.as_ref().storage,
+                    (Addr::unchecked("person1"), "usei".to_string())
+                )
+                .unwrap()
+        );
+    }
+
+    // checks if the withdraw limit is enforced per window
+    #[test]
+    fn withdraw_limit() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // only the owner can set the limit
+        let user_info = mock_info("person1", &[]);
+        let limit_msg = ExecuteMsg::SetWithdrawLimit {
+            limit: Some(Uint128::new(60)),
+            window: 10,
+        };
+        let execute_res = execute(
+            deps.as_mut(),
+            mock_env(),
+            user_info.clone(),
+            limit_msg.clone(),
+        );
+        match execute_res.unwrap_err() {
+            ContractError::NotOwner { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, limit_msg).unwrap();
+
+        // person1 withdraws up to the limit
+        for quantity in [50, 10] {
+            let msg = ExecuteMsg::Withdraw {
+                quantity: Some(Uint128::new(quantity)),
+                denom: None,
+                percent: None,
+                from: None,
+                to: None,
+            };
+            let _res = execute(deps.as_mut(), mock_env(), user_info.clone(), msg).unwrap();
+        }
+
+        // person1 withdraws over the limit in the same window
+        let msg = ExecuteMsg::Withdraw {
+            quantity: Some(Uint128::new(1)),
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let mut env = mock_env();
+        env.block.height += 9;
+        let execute_res = execute(deps.as_mut(), env.clone(), user_info.clone(), msg.clone());
+        match execute_res.unwrap_err() {
+            ContractError::WithdrawLimitExceeded { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // person1 can withdraw again in the next window
+        env.block.height += 1;
+        let _res = execute(deps.as_mut(), env, user_info, msg).unwrap();
+    }
+
+    // checks if the memo is echoed and an oversized one is rejected
+    #[test]
+    fn split_memo() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: Some("invoice-42".to_string()),
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), sender_info.clone(), split_msg).unwrap();
+        assert!(res
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "memo" && attr.value == "invoice-42"));
+
+        // a memo over the limit is rejected
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: Some("a".repeat(MAX_MEMO_LENGTH + 1)),
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg);
+        match res.unwrap_err() {
+            ContractError::MemoTooLong { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if a split resubmitted with the same key is rejected
+    #[test]
+    fn split_idempotency_key() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: Some("payout-1".to_string()),
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            split_msg.clone(),
+        )
+        .unwrap();
+
+        // the same sender resubmits the split
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg.clone());
+        match res.unwrap_err() {
+            ContractError::DuplicateSplit { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // another sender can use the same key
+        let other_info = mock_info("other", &coins(200, "usei"));
+        let _res = execute(deps.as_mut(), mock_env(), other_info, split_msg).unwrap();
+
+        // only the first split of sender was credited
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::WithdrawableAmount {
+                address: Addr::unchecked("person1"),
+                denom: None,
+            },
+        )
+        .unwrap();
+        let user_balance: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(198), user_balance);
+    }
+
+    // checks if the withdrawable amounts of several addresses are returned at once
+    #[test]
+    fn withdrawable_amounts() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        let addresses = vec![
+            Addr::unchecked("person1"),
+            Addr::unchecked("person3"),
+            Addr::unchecked("person2"),
+        ];
+        let query_msg = QueryMsg::WithdrawableAmounts {
+            addresses: addresses.clone(),
+            denom: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let amounts: Vec<(Addr, Uint128)> = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![
+                (Addr::unchecked("person1"), Uint128::new(99)),
+                (Addr::unchecked("person3"), Uint128::zero()),
+                (Addr::unchecked("person2"), Uint128::new(99)),
+            ],
+            amounts
+        );
+
+        // more than 50 addresses are rejected
+        let query_msg = QueryMsg::WithdrawableAmounts {
+            addresses: addresses.into_iter().cycle().take(51).collect(),
+            denom: None,
+        };
+        assert!(query(deps.as_ref(), mock_env(), query_msg).is_err());
+    }
+
+    // checks if balances exported from one contract can be imported into another
+    #[test]
+    fn export_import_balances() {
+        let mut old_deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            allowed_denoms: Some(vec!["usei".to_string(), "uatom".to_string()]),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            old_deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // person1 and person2 get 99 each, person3 gets 495, uatom is left out of the export
+        for (recipient, funds) in [
+            ("person2", coins(200, "usei")),
+            ("person3", coins(1000, "usei")),
+            ("person3", coins(1000, "uatom")),
+        ] {
+            let split_msg = ExecuteMsg::Split {
+                recipient1: Addr::unchecked("person1"),
+                recipient2: Addr::unchecked(recipient),
+                memo: None,
+                idempotency_key: None,
+                vesting: None,
+                expires_at: None,
+                fee_on_top: false,
+                coupon: None,
+                nonce: None,
+                lock_until: None,
+                referrer: None,
+            };
+            let sender_info = mock_info("sender", &funds);
+            let _res = execute(old_deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        }
+
+        // export two pages
+        let mut exported = vec![];
+        let mut start_after = None;
+        loop {
+            let export_msg = QueryMsg::ExportBalances {
+                start_after,
+                limit: Some(2),
+            };
+            let res = query(old_deps.as_ref(), mock_env(), export_msg).unwrap();
+            let page: ExportBalancesResponse = from_binary(&res).unwrap();
+            match page.entries.last() {
+                Some((address, _)) => start_after = Some(address.clone()),
+                None => break,
+            }
+            exported.extend(page.entries);
+        }
+        assert_eq!(
+            vec![
+                (Addr::unchecked("person1"), Uint128::new(594)),
+                (Addr::unchecked("person2"), Uint128::new(99)),
+                (Addr::unchecked("person3"), Uint128::new(495)),
+            ],
+            exported
+        );
+
+        // the new contract only imports while paused
+        let mut new_deps = mock_dependencies();
+        let _res = instantiate(
+            new_deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            InstantiateMsg::default(),
+        )
+        .unwrap();
+        let import_msg = ExecuteMsg::ImportBalances { entries: exported };
+        let res = execute(
+            new_deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            import_msg.clone(),
+        );
+        match res.unwrap_err() {
+            ContractError::NotPaused { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let _res = execute(
+            new_deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            ExecuteMsg::SetPaused { paused: true },
+        )
+        .unwrap();
+        let _res = execute(
+            new_deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            import_msg.clone(),
+        )
+        .unwrap();
+
+        // both contracts export the same balances
+        let export_msg = QueryMsg::ExportBalances {
+            start_after: None,
+            limit: None,
+        };
+        let old_res = query(old_deps.as_ref(), mock_env(), export_msg.clone()).unwrap();
+        let new_res = query(new_deps.as_ref(), mock_env(), export_msg).unwrap();
+        assert_eq!(old_res, new_res);
+
+        // importing the same batch again is rejected
+        let res = execute(new_deps.as_mut(), mock_env(), creator_info, import_msg);
+        match res.unwrap_err() {
+            ContractError::AlreadyImported { address } => assert_eq!("person1", address),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if balances and fees are reported in whole tokens next to the raw amount
+    #[test]
+    fn display_amounts() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99) with a fee of 2
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        let query_msg = QueryMsg::DisplayBalance {
+            address: Addr::unchecked("person1"),
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let balance: DisplayAmount = from_binary(&res).unwrap();
+        assert_eq!(
+            DisplayAmount {
+                raw: Uint128::new(99),
+                display: "0.000099".to_string(),
+            },
+            balance
+        );
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::DisplayFees {}).unwrap();
+        let fees: DisplayAmount = from_binary(&res).unwrap();
+        assert_eq!(
+            DisplayAmount {
+                raw: Uint128::new(2),
+                display: "0.000002".to_string(),
+            },
+            fees
+        );
+    }
+
+    // checks if the lifetime total of a recipient survives withdrawals
+    #[test]
+    fn total_earned() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // person1 withdraws everything
+        let user_info = mock_info("person1", &[]);
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user_info, withdraw_msg).unwrap();
+
+        let query_msg = QueryMsg::TotalEarned {
+            address: Addr::unchecked("person1"),
+            denom: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let earned: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(99), earned);
+    }
+
+    // checks if every split is counted
+    #[test]
+    fn split_count() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender splits twice
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        for _ in 0..2 {
+            let _res = execute(
+                deps.as_mut(),
+                mock_env(),
+                sender_info.clone(),
+                split_msg.clone(),
+            )
+            .unwrap();
+        }
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Stats {}).unwrap();
+        let stats: StatsResponse = from_binary(&res).unwrap();
+        assert_eq!(2, stats.split_count);
+    }
+
+    // checks if every split is recorded in the history
+    #[test]
+    fn history() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender1 splits 200 coins, then sender2 splits 1000 coins at a later height
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let sender1_info = mock_info("sender1", &coins(200, "usei"));
+        let _res = execute(deps.as_mut(), mock_env(), sender1_info, split_msg.clone()).unwrap();
+        let mut env = mock_env();
+        env.block.height += 5;
+        env.block.time = env.block.time.plus_seconds(30);
+        let sender2_info = mock_info("sender2", &coins(1000, "usei"));
+        let _res = execute(deps.as_mut(), env.clone(), sender2_info, split_msg).unwrap();
+
+        let history_msg = QueryMsg::History {
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), history_msg).unwrap();
+        let history: HistoryResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![
+                (
+                    2,
+                    DepositRecord {
+                        sender: Addr::unchecked("sender2"),
+                        recipients: vec![Addr::unchecked("person1"), Addr::unchecked("person2")],
+                        amount: coins(1000, "usei"),
+                        fee: coins(10, "usei"),
+                        height: env.block.height,
+                        time: env.block.time,
+                    }
+                ),
+                (
+                    1,
+                    DepositRecord {
+                        sender: Addr::unchecked("sender1"),
+                        recipients: vec![Addr::unchecked("person1"), Addr::unchecked("person2")],
+                        amount: coins(200, "usei"),
+                        fee: coins(2, "usei"),
+                        height: mock_env().block.height,
+                        time: mock_env().block.time,
+                    }
+                ),
+            ],
+            history.records
+        );
+
+        // pages continue after the last index
+        let history_msg = QueryMsg::History {
+            start_after: Some(2),
+            limit: Some(1),
+        };
+        let res = query(deps.as_ref(), mock_env(), history_msg).unwrap();
+        let history: HistoryResponse = from_binary(&res).unwrap();
+        assert_eq!(1, history.records.len());
+        assert_eq!(1, history.records[0].0);
+    }
+
+    // checks if the distinct recipients of a sender are listed
+    #[test]
+    fn recipients_by_sender() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender splits to person1 and person2, then to person2 and person3
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        for (recipient1, recipient2) in [("person1", "person2"), ("person2", "person3")] {
+            let split_msg = ExecuteMsg::Split {
+                recipient1: Addr::unchecked(recipient1),
+                recipient2: Addr::unchecked(recipient2),
+                memo: None,
+                idempotency_key: None,
+                vesting: None,
+                expires_at: None,
+                fee_on_top: false,
+                coupon: None,
+                nonce: None,
+                lock_until: None,
+                referrer: None,
+            };
+            let _res = execute(deps.as_mut(), mock_env(), sender_info.clone(), split_msg).unwrap();
+        }
+
+        // another sender's recipients are not listed
+        let other_info = mock_info("other", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person4"),
+            recipient2: Addr::unchecked("person5"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), other_info, split_msg).unwrap();
+
+        let query_msg = QueryMsg::RecipientsBySender {
+            sender: Addr::unchecked("sender"),
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let value: RecipientsResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![
+                Addr::unchecked("person1"),
+                Addr::unchecked("person2"),
+                Addr::unchecked("person3"),
+            ],
+            value.recipients
+        );
+
+        // pages continue after the last recipient
+        let query_msg = QueryMsg::RecipientsBySender {
+            sender: Addr::unchecked("sender"),
+            start_after: Some(Addr::unchecked("person2")),
+            limit: Some(1),
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let value: RecipientsResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![Addr::unchecked("person3")], value.recipients);
+    }
+
+    // checks if every stored balance is counted until it is withdrawn
+    #[test]
+    fn map_size() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            allowed_denoms: Some(vec!["usei".to_string(), "uatom".to_string()]),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // person1 and person2 get usei twice and uatom once
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        for funds in [coins(200, "usei"), coins(200, "usei"), coins(200, "uatom")] {
+            let sender_info = mock_info("sender", &funds);
+            let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg.clone()).unwrap();
+        }
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::MapSize {}).unwrap();
+        let size: u64 = from_binary(&res).unwrap();
+        assert_eq!(4, size);
+
+        // a partial withdrawal keeps the balance, a full one removes it
+        let user_info = mock_info("person1", &[]);
+        for quantity in [Some(Uint128::new(10)), None] {
+            let withdraw_msg = ExecuteMsg::Withdraw {
+                quantity,
+                denom: None,
+                percent: None,
+                from: None,
+                to: None,
+            };
+            let _res = execute(deps.as_mut(), mock_env(), user_info.clone(), withdraw_msg).unwrap();
+        }
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::MapSize {}).unwrap();
+        let size: u64 = from_binary(&res).unwrap();
+        assert_eq!(3, size);
+    }
+
+    // checks if distinct recipients are counted until they withdraw everything
+    #[test]
+    fn recipient_count() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            allowed_denoms: Some(vec!["usei".to_string(), "uatom".to_string()]),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender splits two denoms twice to the same recipients
+        let sender_info = mock_info("sender", &[coin(200, "usei"), coin(1000, "uatom")]);
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        for _ in 0..2 {
+            let _res = execute(
+                deps.as_mut(),
+                mock_env(),
+                sender_info.clone(),
+                split_msg.clone(),
+            )
+            .unwrap();
+        }
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Stats {}).unwrap();
+        let stats: StatsResponse = from_binary(&res).unwrap();
+        assert_eq!(2, stats.recipient_count);
+
+        // person1 withdraws one denom and is still counted
+        let user_info = mock_info("person1", &[]);
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: Some("uatom".to_string()),
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user_info.clone(), withdraw_msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Stats {}).unwrap();
+        let stats: StatsResponse = from_binary(&res).unwrap();
+        assert_eq!(2, stats.recipient_count);
+
+        // person1 withdraws the last denom
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user_info, withdraw_msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Stats {}).unwrap();
+        let stats: StatsResponse = from_binary(&res).unwrap();
+        assert_eq!(1, stats.recipient_count);
+    }
+
+    // checks if the preview matches what an actual withdrawal sends
+    #[test]
+    fn preview_withdraw() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        let user_info = mock_info("person1", &[]);
+        for quantity in [Some(Uint128::new(50)), None] {
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::PreviewWithdraw {
+                    address: Addr::unchecked("person1"),
+                    quantity,
+                    denom: None,
+                    percent: None,
+                },
+            )
+            .unwrap();
+            let preview: PreviewWithdrawResponse = from_binary(&res).unwrap();
+
+            let msg = ExecuteMsg::Withdraw {
+                quantity,
+                denom: None,
+                percent: None,
+                from: None,
+                to: None,
+            };
+            let execute_res = execute(deps.as_mut(), mock_env(), user_info.clone(), msg).unwrap();
+            let sub_msg = execute_res.messages.first().expect("no message");
+            assert_eq!(
+                sub_msg.msg,
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "person1".into(),
+                    amount: preview.amount,
+                })
+            );
+
+            // the remaining balance matches the preview
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::WithdrawableAmount {
+                    address: Addr::unchecked("person1"),
+                    denom: None,
+                },
+            )
+            .unwrap();
+            let user_balance: Uint128 = from_binary(&res).unwrap();
+            assert_eq!(preview.remaining, user_balance);
+        }
+
+        // previewing more than the balance fails like the withdrawal would
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::PreviewWithdraw {
+                address: Addr::unchecked("person2"),
+                quantity: Some(Uint128::new(100)),
+                denom: None,
+                percent: None,
+            },
+        );
+        assert!(res.is_err());
+    }
+
+    // checks if the sender can reclaim a deposit within the window
+    #[test]
+    fn reclaim() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            reclaim_window: Some(10),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // sender reclaims the deposit within the window
+        let sender_info = mock_info("sender", &[]);
+        let mut env = mock_env();
+        env.block.height += 10;
+        let execute_res = execute(
+            deps.as_mut(),
+            env.clone(),
+            sender_info.clone(),
+            ExecuteMsg::Reclaim {},
+        )
+        .unwrap();
+        let sub_msg = execute_res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "sender".into(),
+                amount: coins(198, "usei"),
+            })
+        );
+
+        // checks the balance of person1 after the reclaim
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::WithdrawableAmount {
+                address: Addr::unchecked("person1"),
+                denom: None,
+            },
+        )
+        .unwrap();
+        let user_balance: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::zero(), user_balance);
+
+        // the deposit can only be reclaimed once
+        let execute_res = execute(
+            deps.as_mut(),
+            env,
+            sender_info.clone(),
+            ExecuteMsg::Reclaim {},
+        );
+        match execute_res.unwrap_err() {
+            ContractError::NoDeposit { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if a deposit cannot be reclaimed from funds others sent after a withdrawal
+    #[test]
+    fn reclaim_after_withdraw() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            reclaim_window: Some(10),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg.clone()).unwrap();
+
+        // person1 withdraws the deposit, then another sender splits the same amount
+        let user_info = mock_info("person1", &[]);
+        let msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user_info, msg).unwrap();
+        let mut env = mock_env();
+        env.block.height += 1;
+        let other_info = mock_info("other", &coins(200, "usei"));
+        let _res = execute(deps.as_mut(), env.clone(), other_info, split_msg).unwrap();
+
+        // the sender cannot take back the other sender's share
+        let sender_info = mock_info("sender", &[]);
+        let execute_res = execute(deps.as_mut(), env, sender_info, ExecuteMsg::Reclaim {});
+        match execute_res.unwrap_err() {
+            ContractError::DepositWithdrawn { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        assert_eq!(
+            Uint128::new(99),
+            AMOUNTS
+                .load(
+                    deps.as_ref().storage,
+                    (Addr::unchecked("person1"), "usei".to_string())
+                )
+                .unwrap()
+        );
+    }
+
+    // checks if a reclaim keeps the vesting another sender set on the recipient
+    #[test]
+    fn reclaim_keeps_other_vesting() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            reclaim_window: Some(10),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // alice sends bob 990 coins that fully vest
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("bob"),
+            recipient2: Addr::unchecked("carol"),
+            memo: None,
+            idempotency_key: None,
+            vesting: Some(VestingTerms {
+                immediate_bps: 0,
+                cliff: 0,
+                duration: 1_000,
+            }),
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let alice_info = mock_info("alice", &coins(2000, "usei"));
+        let _res = execute(deps.as_mut(), mock_env(), alice_info, split_msg).unwrap();
+
+        // bob splits a few coins to himself and dave, then reclaims them
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("bob"),
+            recipient2: Addr::unchecked("dave"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let bob_info = mock_info("bob", &coins(10, "usei"));
+        let _res = execute(deps.as_mut(), mock_env(), bob_info, split_msg).unwrap();
+        let bob_info = mock_info("bob", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), bob_info, ExecuteMsg::Reclaim {}).unwrap();
+
+        // alice's coins are still vesting
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::WithdrawableAmount {
+                address: Addr::unchecked("bob"),
+                denom: None,
+            },
+        )
+        .unwrap();
+        let user_balance: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::zero(), user_balance);
+        assert!(VESTING.has(
+            deps.as_ref().storage,
+            (Addr::unchecked("bob"), "usei".to_string())
+        ));
+    }
+
+    // checks if a deposit cannot be reclaimed while withdrawals are frozen
+    #[test]
+    fn reclaim_while_frozen() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            reclaim_window: Some(10),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        WITHDRAWALS_FROZEN
+            .save(deps.as_mut().storage, &true)
+            .unwrap();
+
+        let sender_info = mock_info("sender", &[]);
+        let execute_res = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info,
+            ExecuteMsg::Reclaim {},
+        );
+        match execute_res.unwrap_err() {
+            ContractError::WithdrawalsFrozen {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if a deposit cannot be reclaimed after the window
+    #[test]
+    fn reclaim_after_window() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            reclaim_window: Some(10),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // sender tries to reclaim one block after the window
+        let sender_info = mock_info("sender", &[]);
+        let mut env = mock_env();
+        env.block.height += 11;
+        let execute_res = execute(deps.as_mut(), env, sender_info, ExecuteMsg::Reclaim {});
+        match execute_res.unwrap_err() {
+            ContractError::ReclaimWindowExpired { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // checks if the owner can sweep the whole balance only while paused
+    #[test]
+    fn emergency_sweep() {
+        let mut deps = mock_dependencies_with_balance(&coins(500, "usei"));
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // the owner cannot sweep while the contract is running
+        let sweep_msg = ExecuteMsg::EmergencySweep {
+            to: Addr::unchecked("treasury"),
+        };
+        let execute_res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            sweep_msg.clone(),
+        );
+        match execute_res.unwrap_err() {
+            ContractError::NotPaused { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // the owner pauses the contract and splits are rejected
+        let pause_msg = ExecuteMsg::SetPaused { paused: true };
+        let _res = execute(deps.as_mut(), mock_env(), creator_info.clone(), pause_msg).unwrap();
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let execute_res = execute(deps.as_mut(), mock_env(), sender_info, split_msg);
+        match execute_res.unwrap_err() {
+            ContractError::Paused { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // only the owner can sweep
+        let user_info = mock_info("person1", &[]);
+        let execute_res = execute(deps.as_mut(), mock_env(), user_info, sweep_msg.clone());
+        match execute_res.unwrap_err() {
+            ContractError::NotOwner { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // the owner sweeps the whole balance in a single send
+        let execute_res = execute(deps.as_mut(), mock_env(), creator_info, sweep_msg).unwrap();
+        assert_eq!(1, execute_res.messages.len());
+        let sub_msg = execute_res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "treasury".into(),
+                amount: coins(500, "usei"),
+            })
+        );
+    }
+
+    // checks if the owner can move a stuck balance while paused
+    #[test]
+    fn force_withdraw() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // the owner cannot force a withdrawal while the contract is running
+        let force_msg = ExecuteMsg::ForceWithdraw {
+            recipient: Addr::unchecked("person1"),
+            to: Addr::unchecked("treasury"),
+        };
+        let execute_res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            force_msg.clone(),
+        );
+        match execute_res.unwrap_err() {
+            ContractError::NotPaused { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        let pause_msg = ExecuteMsg::SetPaused { paused: true };
+        let _res = execute(deps.as_mut(), mock_env(), creator_info.clone(), pause_msg).unwrap();
+
+        // only the owner can force a withdrawal
+        let user_info = mock_info("person2", &[]);
+        let execute_res = execute(deps.as_mut(), mock_env(), user_info, force_msg.clone());
+        match execute_res.unwrap_err() {
+            ContractError::NotOwner { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // the owner moves person1's balance to the treasury
+        let execute_res = execute(deps.as_mut(), mock_env(), creator_info, force_msg).unwrap();
+        assert_eq!(1, execute_res.messages.len());
+        let sub_msg = execute_res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "treasury".into(),
+                amount: coins(99, "usei"),
+            })
+        );
+        assert!(execute_res
+            .attributes
+            .contains(&attr("recipient", "person1")));
+
+        // person1's balance is cleared, person2's is untouched
+        let query_msg = QueryMsg::WithdrawableAmount {
+            address: Addr::unchecked("person1"),
+            denom: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let value: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::zero(), value);
+        let query_msg = QueryMsg::WithdrawableAmount {
+            address: Addr::unchecked("person2"),
+            denom: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let value: Uint128 = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(99), value);
+    }
+
+    // checks if every fee rate change is recorded by height
+    #[test]
+    fn fee_rate_history() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // the owner changes the rate, then governance changes it at a later height
+        let env = mock_env();
+        let fee_msg = ExecuteMsg::SetFeeRate { fee_bps: 200 };
+        let _res = execute(deps.as_mut(), env.clone(), creator_info, fee_msg).unwrap();
+        let mut later_env = mock_env();
+        later_env.block.height += 10;
+        let _res = sudo(
+            deps.as_mut(),
+            later_env.clone(),
+            SudoMsg::SetFeeRate { fee_bps: 50 },
+        )
+        .unwrap();
+
+        let history_msg = QueryMsg::FeeRateHistory {
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), history_msg).unwrap();
+        let history: FeeRateHistoryResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![(env.block.height, 200), (later_env.block.height, 50)],
+            history.changes
+        );
+
+        // start_after skips the older change
+        let history_msg = QueryMsg::FeeRateHistory {
+            start_after: Some(env.block.height),
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), history_msg).unwrap();
+        let history: FeeRateHistoryResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![(later_env.block.height, 50)], history.changes);
+    }
+
+    // checks if every kind of action is appended to the audit log in order
+    #[test]
+    fn audit_log() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // sender sends 200 coins to person1 (99) and person2 (99)
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // person1 withdraws, the owner withdraws the fees and changes the fee rate
+        let withdraw_msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let user_info = mock_info("person1", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), user_info, withdraw_msg).unwrap();
+        let creator_info = mock_info("creator", &[]);
+        let withdraw_fees_msg = ExecuteMsg::WithdrawFees {
+            amount: None,
+            denom: None,
+        };
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            withdraw_fees_msg,
+        )
+        .unwrap();
+        let fee_rate_msg = ExecuteMsg::SetFeeRate { fee_bps: 50 };
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            fee_rate_msg,
+        )
+        .unwrap();
+
+        // a donation, a config setter and governance are logged too
+        let donor_info = mock_info("donor", &coins(10, "usei"));
+        let _res = execute(deps.as_mut(), mock_env(), donor_info, ExecuteMsg::Donate {}).unwrap();
+        let min_msg = ExecuteMsg::SetMinWithdrawal {
+            min: Uint128::new(5),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, min_msg).unwrap();
+        let sudo_msg = SudoMsg::SetFeeRate { fee_bps: 20 };
+        let _res = sudo(deps.as_mut(), mock_env(), sudo_msg).unwrap();
+
+        let entry = |action: &str, actor: &str, amount: Vec<Coin>| AuditEntry {
+            action: action.to_string(),
+            actor: Addr::unchecked(actor),
+            amount,
+            height: mock_env().block.height,
+        };
+        let query_msg = QueryMsg::AuditLog {
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let log: AuditLogResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![
+                (1, entry("split", "sender", coins(200, "usei"))),
+                (2, entry("withdraw", "person1", coins(99, "usei"))),
+                (3, entry("withdraw_fees", "creator", coins(2, "usei"))),
+                (4, entry("set_fee_rate", "creator", vec![])),
+                (5, entry("donate", "donor", coins(10, "usei"))),
+                (6, entry("set_min_withdrawal", "creator", vec![])),
+                (7, entry("set_fee_rate", MOCK_CONTRACT_ADDR, vec![])),
+            ],
+            log.entries
+        );
+
+        // the log can be read page by page
+        let query_msg = QueryMsg::AuditLog {
+            start_after: Some(2),
+            limit: Some(1),
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let log: AuditLogResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![(3, entry("withdraw_fees", "creator", coins(2, "usei")))],
+            log.entries
+        );
+    }
+
+    // checks if the owner can change the fee rate
+    #[test]
+    fn set_fee_rate() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
 
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::OwnerQuery {}).unwrap();
-        let value: OwnerResponse = from_binary(&res).unwrap();
+        // only the owner or an admin can change the fee rate
+        let fee_msg = ExecuteMsg::SetFeeRate { fee_bps: 500 };
+        let user_info = mock_info("person1", &[]);
+        let res = execute(deps.as_mut(), mock_env(), user_info, fee_msg.clone());
+        match res.unwrap_err() {
+            ContractError::NotAdmin {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
 
-        // check if the owner is set after initialization
-        assert_eq!(info.sender, value.owner);
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, fee_msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let config: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(500, config.fee_bps);
     }
 
-    // checks if the sent fund was split and balance of recipient updated
+    // checks if an admin added by the owner can pause until removed
     #[test]
-    fn split_transfer() {
+    fn admins() {
         let mut deps = mock_dependencies();
 
-        let instantiate_msg = InstantiateMsg {};
+        let instantiate_msg = InstantiateMsg::default();
         let creator_info = mock_info("creator", &[]);
-        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
 
-        // send 200 coins from the sender
-        let sender_info = mock_info("sender", &coins(200, "usei"));
-        let split_msg = ExecuteMsg::Split {
-            recipient1: Addr::unchecked("person1"),
-            recipient2: Addr::unchecked("person2"),
+        // a non-admin can neither pause nor add admins
+        let pause_msg = ExecuteMsg::SetPaused { paused: true };
+        let operator_info = mock_info("operator", &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            operator_info.clone(),
+            pause_msg.clone(),
+        );
+        match res.unwrap_err() {
+            ContractError::NotAdmin {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let add_msg = ExecuteMsg::AddAdmin {
+            admin: Addr::unchecked("operator"),
         };
-        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            operator_info.clone(),
+            add_msg.clone(),
+        );
+        match res.unwrap_err() {
+            ContractError::NotOwner {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
 
-        // check the balance of recipient 1
-        let res_1 = query(
-            deps.as_ref(),
+        // once added by the owner, the admin pauses
+        let _res = execute(deps.as_mut(), mock_env(), creator_info.clone(), add_msg).unwrap();
+        let _res = execute(
+            deps.as_mut(),
             mock_env(),
-            QueryMsg::WithdrawableAmount {
-                address: Addr::unchecked("person1"),
-            },
+            operator_info.clone(),
+            pause_msg.clone(),
         )
         .unwrap();
-        let user_1_balance: u128 = from_binary(&res_1).unwrap();
-        assert_eq!(99, user_1_balance);
+        assert!(PAUSED.load(deps.as_ref().storage).unwrap());
 
-        // check the balance of recipient 2
-        let res_2 = query(
-            deps.as_ref(),
+        // an admin cannot transfer the ownership
+        let propose_msg = ExecuteMsg::ProposeOwner {
+            owner: Addr::unchecked("operator"),
+        };
+        let res = execute(
+            deps.as_mut(),
             mock_env(),
-            QueryMsg::WithdrawableAmount {
-                address: Addr::unchecked("person2"),
-            },
-        )
-        .unwrap();
-        let user_2_balance: u128 = from_binary(&res_2).unwrap();
-        assert_eq!(99, user_2_balance);
+            operator_info.clone(),
+            propose_msg,
+        );
+        match res.unwrap_err() {
+            ContractError::NotOwner {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // a removed admin is rejected again
+        let remove_msg = ExecuteMsg::RemoveAdmin {
+            admin: Addr::unchecked("operator"),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), creator_info, remove_msg).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), operator_info, pause_msg);
+        match res.unwrap_err() {
+            ContractError::NotAdmin {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
     }
 
-    // checks if the old balance is updated for the same recipients
+    // checks if governance can change the fee rate and pause without the owner
     #[test]
-    fn update_old_balance() {
+    fn sudo_parameters() {
         let mut deps = mock_dependencies();
 
-        let instantiate_msg = InstantiateMsg {};
+        let instantiate_msg = InstantiateMsg::default();
         let creator_info = mock_info("creator", &[]);
         let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
 
-        // sender sends 200 coins twice to person1 and person2
-        let sender_info = mock_info("sender", &coins(200, "usei"));
-        let split_msg = ExecuteMsg::Split {
-            recipient1: Addr::unchecked("person1"),
-            recipient2: Addr::unchecked("person2"),
-        };
-
-        let _res1 = execute(
+        // governance changes the fee rate
+        let _res = sudo(
             deps.as_mut(),
             mock_env(),
-            sender_info.clone(),
-            split_msg.clone(),
+            SudoMsg::SetFeeRate { fee_bps: 250 },
         )
         .unwrap();
-        let _res2 = execute(
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let config: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(250, config.fee_bps);
+
+        // a fee rate over 100% is rejected
+        let res = sudo(
             deps.as_mut(),
             mock_env(),
-            sender_info.clone(),
-            split_msg.clone(),
-        )
-        .unwrap();
+            SudoMsg::SetFeeRate { fee_bps: 10_001 },
+        );
+        match res.unwrap_err() {
+            ContractError::InvalidFeeBps { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
 
-        // check the updated balance of person2
-        let res = query(
-            deps.as_ref(),
+        // governance pauses the contract and splits are rejected
+        let _res = sudo(
+            deps.as_mut(),
             mock_env(),
-            QueryMsg::WithdrawableAmount {
-                address: Addr::unchecked("person2"),
-            },
+            SudoMsg::SetPaused { paused: true },
         )
         .unwrap();
-        let user_balance: u128 = from_binary(&res).unwrap();
-        assert_eq!(198, user_balance);
+        let sender_info = mock_info("sender", &coins(200, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), sender_info, split_msg);
+        match res.unwrap_err() {
+            ContractError::Paused { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
     }
 
+    // BONUS - The owner can withdraw fees collected from the contract
     #[test]
-    fn withdraw() {
+    fn withdraw_fees() {
         let mut deps = mock_dependencies();
 
-        let instantiate_msg = InstantiateMsg {};
+        let instantiate_msg = InstantiateMsg::default();
         let creator_info = mock_info("creator", &[]);
-        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+        let _res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            instantiate_msg,
+        )
+        .unwrap();
 
-        // sender sends 200 coins to person1 (99) and person2 (99)
+        // sender sends 200 coin and 2 coins are collected as fees
         let sender_info = mock_info("sender", &coins(200, "usei"));
         let split_msg = ExecuteMsg::Split {
             recipient1: Addr::unchecked("person1"),
             recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
         };
         let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
 
-        // person1 withdraws 50 coins
+        // person1 tries to withdraw fees and fails
         let user_info = mock_info("person1", &[]);
-        let mut msg = ExecuteMsg::Withdraw { quantity: Some(50) };
-        let execute_res = execute(deps.as_mut(), mock_env(), user_info.clone(), msg).unwrap();
-        assert_eq!(1, execute_res.messages.len());
+        let msg = ExecuteMsg::WithdrawFees {
+            amount: None,
+            denom: None,
+        };
+        let execute_res = execute(deps.as_mut(), mock_env(), user_info, msg.clone());
+        match execute_res.unwrap_err() {
+            ContractError::NotOwner { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
 
-        let sub_msg = execute_res.messages.get(0).expect("no message");
+        // owner withdraws fees from the contract
+        let execute_res = execute(deps.as_mut(), mock_env(), creator_info.clone(), msg).unwrap();
+        let sub_msg = execute_res.messages.first().expect("no message");
         assert_eq!(
             sub_msg.msg,
             CosmosMsg::Bank(BankMsg::Send {
-                to_address: "person1".into(),
-                amount: coins(50, "usei"),
+                to_address: "creator".into(),
+                amount: coins(2, "usei"),
             })
         );
+    }
 
-        // checks the balance of person1 after the partial withdraw
-        let res = query(
-            deps.as_ref(),
+    // checks if the owner can withdraw part of the fees
+    #[test]
+    fn withdraw_partial_fees() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg::default();
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(
+            deps.as_mut(),
             mock_env(),
-            QueryMsg::WithdrawableAmount {
-                address: Addr::unchecked("person1"),
-            },
+            creator_info.clone(),
+            instantiate_msg,
         )
         .unwrap();
-        let user_balance: u128 = from_binary(&res).unwrap();
-        assert_eq!(49, user_balance);
 
-        // person1 withdraws the entire balance (49)
-        msg = ExecuteMsg::Withdraw { quantity: None };
-        let execute_res = execute(deps.as_mut(), mock_env(), user_info, msg).unwrap();
-        assert_eq!(1, execute_res.messages.len());
+        // sender sends 1000 coins and 10 coins are collected as fees
+        let sender_info = mock_info("sender", &coins(1000, "usei"));
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+
+        // more than the collected fees cannot be withdrawn
+        let msg = ExecuteMsg::WithdrawFees {
+            amount: Some(Uint128::new(11)),
+            denom: None,
+        };
+        let execute_res = execute(deps.as_mut(), mock_env(), creator_info.clone(), msg);
+        match execute_res.unwrap_err() {
+            ContractError::ExceededQuantity { .. } => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
 
-        let sub_msg = execute_res.messages.get(0).expect("no message");
+        // owner withdraws 4 of the 10 coins
+        let msg = ExecuteMsg::WithdrawFees {
+            amount: Some(Uint128::new(4)),
+            denom: None,
+        };
+        let execute_res = execute(deps.as_mut(), mock_env(), creator_info.clone(), msg).unwrap();
+        let sub_msg = execute_res.messages.first().expect("no message");
         assert_eq!(
             sub_msg.msg,
             CosmosMsg::Bank(BankMsg::Send {
-                to_address: "person1".into(),
-                amount: coins(49, "usei"),
+                to_address: "creator".into(),
+                amount: coins(4, "usei"),
             })
         );
+        let fee = FEE_BY_DENOM
+            .load(deps.as_ref().storage, "usei".to_string())
+            .unwrap();
+        assert_eq!(Uint128::new(6), fee);
 
-        // checks the balance of person1 after withdrawal
-        let res = query(
-            deps.as_ref(),
+        // the rest is drained without an amount
+        let msg = ExecuteMsg::WithdrawFees {
+            amount: None,
+            denom: None,
+        };
+        let execute_res = execute(deps.as_mut(), mock_env(), creator_info, msg).unwrap();
+        let sub_msg = execute_res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "creator".into(),
+                amount: coins(6, "usei"),
+            })
+        );
+    }
+
+    // checks if the remainders of odd splits accrue as dust the owner can withdraw
+    #[test]
+    fn dust() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            track_dust: true,
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // 201 coins split into a fee of 2, 99 for each recipient and 1 of dust, twice
+        for _ in 0..2 {
+            let sender_info = mock_info("sender", &coins(201, "usei"));
+            let split_msg = ExecuteMsg::Split {
+                recipient1: Addr::unchecked("person1"),
+                recipient2: Addr::unchecked("person2"),
+                memo: None,
+                idempotency_key: None,
+                vesting: None,
+                expires_at: None,
+                fee_on_top: false,
+                coupon: None,
+                nonce: None,
+                lock_until: None,
+                referrer: None,
+            };
+            let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        }
+        assert_eq!(
+            Uint128::new(4),
+            FEE_BY_DENOM
+                .load(deps.as_ref().storage, "usei".to_string())
+                .unwrap()
+        );
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Dust {}).unwrap();
+        let dust: Vec<Coin> = from_binary(&res).unwrap();
+        assert_eq!(coins(2, "usei"), dust);
+
+        // only the owner can withdraw the dust
+        let user_info = mock_info("person1", &[]);
+        let res = execute(
+            deps.as_mut(),
             mock_env(),
-            QueryMsg::WithdrawableAmount {
-                address: Addr::unchecked("person1"),
-            },
+            user_info,
+            ExecuteMsg::WithdrawDust {},
+        );
+        match res.unwrap_err() {
+            ContractError::NotOwner {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        let creator_info = mock_info("creator", &[]);
+        let execute_res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info.clone(),
+            ExecuteMsg::WithdrawDust {},
         )
         .unwrap();
-        let user_balance: u128 = from_binary(&res).unwrap();
-        assert_eq!(0, user_balance);
+        let sub_msg = execute_res.messages.first().expect("no message");
+        assert_eq!(
+            sub_msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "creator".into(),
+                amount: coins(2, "usei"),
+            })
+        );
+
+        // nothing is left afterwards
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            creator_info,
+            ExecuteMsg::WithdrawDust {},
+        );
+        match res.unwrap_err() {
+            ContractError::NoDust {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
     }
 
-    // BONUS - The owner can withdraw fees collected from the contract
+    // checks if the liabilities follow the splits and withdrawals
     #[test]
-    fn withdraw_fees() {
+    fn total_liabilities() {
         let mut deps = mock_dependencies();
 
-        let instantiate_msg = InstantiateMsg {};
+        let instantiate_msg = InstantiateMsg {
+            allowed_denoms: Some(vec!["usei".to_string(), "uatom".to_string()]),
+            ..InstantiateMsg::default()
+        };
         let creator_info = mock_info("creator", &[]);
-        let _res = instantiate(
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // 198 usei and 990 uatom are owed after the fees
+        let sender_info = mock_info("sender", &[coin(200, "usei"), coin(1000, "uatom")]);
+        let split_msg = ExecuteMsg::Split {
+            recipient1: Addr::unchecked("person1"),
+            recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::TotalLiabilities {}).unwrap();
+        let total: TotalLiabilitiesResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![coin(990, "uatom"), coin(198, "usei")],
+            total.liabilities
+        );
+
+        // person1 withdraws part of the usei and all of the uatom
+        let user_info = mock_info("person1", &[]);
+        let msg = ExecuteMsg::Withdraw {
+            quantity: Some(Uint128::new(40)),
+            denom: None,
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user_info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::Withdraw {
+            quantity: None,
+            denom: Some("uatom".to_string()),
+            percent: None,
+            from: None,
+            to: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), user_info, msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::TotalLiabilities {}).unwrap();
+        let total: TotalLiabilitiesResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![coin(495, "uatom"), coin(158, "usei")],
+            total.liabilities
+        );
+
+        // person2 withdraws everything, the uatom liabilities are settled
+        let user_info = mock_info("person2", &[]);
+        let _res = execute(
             deps.as_mut(),
             mock_env(),
-            creator_info.clone(),
-            instantiate_msg,
+            user_info,
+            ExecuteMsg::WithdrawAll {},
         )
         .unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::TotalLiabilities {}).unwrap();
+        let total: TotalLiabilitiesResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(59, "usei")], total.liabilities);
+    }
 
-        // sender sends 200 coin and 2 coins are collected as fees
-        let sender_info = mock_info("sender", &coins(200, "usei"));
+    // checks if fees accrue per denom and can be withdrawn one denom at a time
+    #[test]
+    fn fees_by_denom() {
+        let mut deps = mock_dependencies();
+
+        let instantiate_msg = InstantiateMsg {
+            allowed_denoms: Some(vec!["usei".to_string(), "uatom".to_string()]),
+            ..InstantiateMsg::default()
+        };
+        let creator_info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), creator_info, instantiate_msg).unwrap();
+
+        // 2 usei and 10 uatom are collected as fees
+        let sender_info = mock_info("sender", &[coin(200, "usei"), coin(1000, "uatom")]);
         let split_msg = ExecuteMsg::Split {
             recipient1: Addr::unchecked("person1"),
             recipient2: Addr::unchecked("person2"),
+            memo: None,
+            idempotency_key: None,
+            vesting: None,
+            expires_at: None,
+            fee_on_top: false,
+            coupon: None,
+            nonce: None,
+            lock_until: None,
+            referrer: None,
         };
         let _res = execute(deps.as_mut(), mock_env(), sender_info, split_msg).unwrap();
 
-        // person1 tries to withdraw fees and fails
-        let user_info = mock_info("person1", &[]);
-        let msg = ExecuteMsg::WithdrawFees {};
-        let execute_res = execute(deps.as_mut(), mock_env(), user_info, msg.clone());
-        match execute_res.unwrap_err() {
-            ContractError::NotOwner { .. } => {}
-            e => panic!("unexpected error: {:?}", e),
-        }
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::TotalFees {}).unwrap();
+        let total: TotalFeesResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(10, "uatom"), coin(2, "usei")], total.fees);
 
-        // owner withdraws fees from the contract
+        // the owner drains only the uatom fees
+        let creator_info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::WithdrawFees {
+            amount: None,
+            denom: Some("uatom".to_string()),
+        };
         let execute_res = execute(deps.as_mut(), mock_env(), creator_info.clone(), msg).unwrap();
-        let sub_msg = execute_res.messages.get(0).expect("no message");
+        let sub_msg = execute_res.messages.first().expect("no message");
         assert_eq!(
             sub_msg.msg,
             CosmosMsg::Bank(BankMsg::Send {
                 to_address: "creator".into(),
-                amount: coins(2, "usei"),
+                amount: coins(10, "uatom"),
             })
         );
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::TotalFees {}).unwrap();
+        let total: TotalFeesResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(2, "usei")], total.fees);
+
+        // nothing is left to withdraw in uatom
+        let msg = ExecuteMsg::WithdrawFees {
+            amount: None,
+            denom: Some("uatom".to_string()),
+        };
+        let execute_res = execute(deps.as_mut(), mock_env(), creator_info, msg);
+        match execute_res.unwrap_err() {
+            ContractError::NoFees {} => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
     }
 }
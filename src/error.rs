@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -12,6 +12,36 @@ pub enum ContractError {
     #[error("Quantity exceeds withdrawable amount")]
     ExceededQuantity {},
 
+    #[error("Quantity and percent cannot both be set")]
+    ConflictingWithdrawArgs {},
+
+    #[error("Percent must be between 1 and 100")]
+    InvalidPercent {},
+
+    #[error("Partial withdrawals must be at least {min}")]
+    WithdrawalTooSmall { min: Uint128 },
+
+    #[error("With the fee on top, the amount sent must be a split amount plus its fee, such as {closest}")]
+    InvalidFeeOnTop { closest: Uint128 },
+
+    #[error("Expiry must be in the future")]
+    InvalidExpiry {},
+
+    #[error("Lock height must be in the future")]
+    InvalidLockHeight {},
+
+    #[error("Balance is locked until height {until}")]
+    StillLocked { until: u64 },
+
+    #[error("No balance has expired")]
+    NotExpired {},
+
+    #[error("Nothing to withdraw")]
+    NothingToWithdraw {},
+
+    #[error("No payouts are queued")]
+    NoPendingPayouts {},
+
     #[error("Wrong coin sent")]
     WrongCoinSent {},
 
@@ -20,4 +50,154 @@ pub enum ContractError {
 
     #[error("Sender is not owner")]
     NotOwner {},
+
+    #[error("Sender is neither owner nor admin")]
+    NotAdmin {},
+
+    #[error("Contract is paused")]
+    Paused {},
+
+    #[error("Contract is not paused")]
+    NotPaused {},
+
+    #[error("Withdrawals are frozen")]
+    WithdrawalsFrozen {},
+
+    #[error("Contract is finalized")]
+    Finalized {},
+
+    #[error("No fees to withdraw")]
+    NoFees {},
+
+    #[error("No dust to withdraw")]
+    NoDust {},
+
+    #[error("Reclaiming deposits is disabled")]
+    ReclaimDisabled {},
+
+    #[error("No deposit to reclaim")]
+    NoDeposit {},
+
+    #[error("Reclaim window has expired")]
+    ReclaimWindowExpired {},
+
+    #[error("Deposit was already withdrawn by a recipient")]
+    DepositWithdrawn {},
+
+    #[error("Fee cannot exceed 10000 basis points")]
+    InvalidFeeBps {},
+
+    #[error("Coupon is unknown or already used")]
+    InvalidCoupon {},
+
+    #[error("Fee and platform cut cannot exceed 10000 basis points")]
+    InvalidPlatformBps {},
+
+    #[error("Tip cannot exceed 10000 basis points")]
+    InvalidTipBps {},
+
+    #[error("Referrer cut cannot exceed 10000 basis points")]
+    InvalidReferrerBps {},
+
+    #[error("Referrer cannot be the contract, the sender or a recipient")]
+    InvalidReferrer {},
+
+    #[error("Payouts plus the fee must equal {expected}, got {got}")]
+    AmountMismatch { expected: Uint128, got: Uint128 },
+
+    #[error("Amount is below the minimum of {min}")]
+    AmountBelowMinimum { min: Uint128 },
+
+    #[error("Deposit exceeds the maximum of {max}")]
+    DepositTooLarge { max: Uint128 },
+
+    #[error("Denom must be 3 to 128 characters, starting with a letter")]
+    InvalidDenom {},
+
+    #[error("Duplicate address in initial balances: {address}")]
+    DuplicateInitialBalance { address: String },
+
+    #[error("Balance already imported for {address}")]
+    AlreadyImported { address: String },
+
+    #[error("Recipient cannot be the contract itself")]
+    RecipientIsContract {},
+
+    #[error("Recipient address cannot be empty")]
+    InvalidRecipient {},
+
+    #[error("Unknown alias {alias}")]
+    UnknownAlias { alias: String },
+
+    #[error("Alias {alias} is already registered")]
+    AliasTaken { alias: String },
+
+    #[error("Memo exceeds {max} bytes")]
+    MemoTooLong { max: usize },
+
+    #[error("At most {max} expiring or locked parts per balance")]
+    TooManyParts { max: usize },
+
+    #[error("At most {max} recipients per split")]
+    TooManyRecipients { max: u32 },
+
+    #[error("Maximum recipients must be between 1 and {cap}")]
+    InvalidMaxRecipients { cap: u32 },
+
+    #[error("Withdraw limit exceeded for this window")]
+    WithdrawLimitExceeded {},
+
+    #[error("Withdrawals are on cooldown until height {retry_at}")]
+    CooldownActive { retry_at: u64 },
+
+    #[error("Unknown reply id: {id}")]
+    UnknownReplyId { id: u64 },
+
+    #[error("This message does not accept funds")]
+    UnexpectedFunds {},
+
+    #[error("Total fee overflows")]
+    FeeOverflow {},
+
+    #[error("A split with this idempotency key was already submitted")]
+    DuplicateSplit {},
+
+    #[error("Invalid nonce, expected {expected}")]
+    InvalidNonce { expected: u64 },
+
+    #[error("No balance held in {denom}")]
+    DenomNotHeld { denom: String },
+
+    #[error("Owner cannot be the fee recipient")]
+    OwnerIsFeeRecipient {},
+
+    #[error("Invalid vesting terms")]
+    InvalidVesting {},
+
+    #[error("A recipient still has an active vesting in this denom")]
+    VestingActive {},
+
+    #[error("Amount after fees is too small to split between the recipients")]
+    AmountTooSmall {},
+
+    #[error("Sender is not allowed to split")]
+    SenderNotAllowed {},
+
+    #[error("Address is blocked")]
+    RecipientBlocked {},
+
+    #[error("Shares must sum to 10000 basis points")]
+    InvalidShares {},
+
+    #[error("Contract version {version} is not valid semver")]
+    InvalidVersion { version: String },
+
+    #[error("Cannot migrate from contract {contract}")]
+    WrongContract { contract: String },
+
+    #[error("Cannot migrate to an older version than {stored}")]
+    CannotDowngrade { stored: String },
+
+    #[error("Cannot migrate the state of the first release, it has no config")]
+    UnsupportedLayout {},
 }
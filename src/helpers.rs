@@ -1,16 +1,205 @@
+use crate::state::{Config, RoundingMode};
 use crate::ContractError;
-use cosmwasm_std::Coin;
+use cosmwasm_std::{Coin, Uint128};
 
-// validate if the funded coin is usei and return it
-pub fn validate_and_extract_coin(sent_funds: &[Coin]) -> Result<Coin, ContractError> {
+// fee, amount each recipient gets and rounding remainder of a split,
+// fee + amount_each * recipients + remainder == gross
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SplitBreakdown {
+    pub fee: u128,
+    pub amount_each: u128,
+    pub remainder: u128,
+}
+
+// split a gross amount between n recipients after the fee of the config,
+// raised to the minimum fee
+pub fn compute_split(gross: u128, config: &Config, min_fee: u128, n: u32) -> SplitBreakdown {
+    let fee = compute_fee(Uint128::new(gross), config).u128().max(min_fee);
+    breakdown_after_fee(gross, fee, n)
+}
+
+// returns the fee on an amount, rounded as configured. The products are kept
+// in 256 bits, so the fee of any amount is computed without overflowing
+pub fn compute_fee(amount: Uint128, config: &Config) -> Uint128 {
+    let fee = amount.multiply_ratio(config.fee_bps, 10_000u128);
+    // round up if the division truncated the fee
+    if config.fee_rounding == RoundingMode::Ceil
+        && fee.full_mul(10_000u128) < amount.full_mul(config.fee_bps)
+    {
+        return fee + Uint128::new(1);
+    }
+    fee
+}
+
+// split what is left of a gross amount after the fee between n recipients,
+// the fee is capped at the gross amount and no recipient gets anything if n is 0
+pub fn breakdown_after_fee(gross: u128, fee: u128, n: u32) -> SplitBreakdown {
+    let fee = fee.min(gross);
+    let net = gross - fee;
+    let amount_each = net.checked_div(u128::from(n)).unwrap_or_default();
+    SplitBreakdown {
+        fee,
+        amount_each,
+        remainder: net - amount_each * u128::from(n),
+    }
+}
+
+// format an amount in base units as whole tokens with all the decimals, e.g. 99 with 6 as 0.000099
+pub fn format_decimals(raw: Uint128, decimals: u8) -> String {
+    let decimals = usize::from(decimals);
+    if decimals == 0 {
+        return raw.to_string();
+    }
+    let digits = format!("{:0>width$}", raw.to_string(), width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    format!("{}.{}", whole, fraction)
+}
+
+// validate if the funded coin is of an allowed denom and return it,
+// zero-amount coins some wallets attach are ignored
+pub fn validate_and_extract_coin(
+    sent_funds: &[Coin],
+    allowed_denoms: &[String],
+) -> Result<Coin, ContractError> {
+    let sent_funds: Vec<&Coin> = sent_funds
+        .iter()
+        .filter(|sent| !sent.amount.is_zero())
+        .collect();
     if sent_funds.len() != 1 {
         return Err(ContractError::WrongCoinSent {});
     }
-    if sent_funds[0].denom.ne("usei") {
+    validate_denom(&sent_funds[0].denom, allowed_denoms)?;
+    Ok(sent_funds[0].clone())
+}
+
+// validate if the denom follows the format of the bank module: a letter followed by
+// 2 to 127 letters, digits or any of / : . _ -
+pub fn validate_denom_format(denom: &str) -> Result<(), ContractError> {
+    let mut chars = denom.chars();
+    let starts_with_letter = chars.next().is_some_and(|c| c.is_ascii_alphabetic());
+    let valid_chars = chars.all(|c| c.is_ascii_alphanumeric() || "/:._-".contains(c));
+    if !starts_with_letter || !valid_chars || !(3..=128).contains(&denom.len()) {
+        return Err(ContractError::InvalidDenom {});
+    }
+    Ok(())
+}
+
+// validate if the denom is in the allowed denoms
+pub fn validate_denom(denom: &str, allowed_denoms: &[String]) -> Result<(), ContractError> {
+    if !allowed_denoms.iter().any(|allowed| allowed == denom) {
         return Err(ContractError::WrongFundCoin {
-            expected: String::from("usei"),
-            got: sent_funds[0].denom.clone(),
+            expected: allowed_denoms.join(","),
+            got: String::from(denom),
         });
     }
-    Ok(sent_funds[0].clone())
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::Addr;
+
+    fn config(fee_bps: u16, fee_rounding: RoundingMode) -> Config {
+        Config {
+            denom: "usei".to_string(),
+            fee_bps,
+            min_amount: Uint128::zero(),
+            fee_recipient: Addr::unchecked("creator"),
+            fee_rounding,
+        }
+    }
+
+    // checks if an even amount splits without a remainder
+    #[test]
+    fn compute_split_even() {
+        assert_eq!(
+            SplitBreakdown {
+                fee: 2,
+                amount_each: 99,
+                remainder: 0,
+            },
+            compute_split(200, &config(100, RoundingMode::Floor), 0, 2)
+        );
+        assert_eq!(
+            SplitBreakdown {
+                fee: 25,
+                amount_each: 325,
+                remainder: 0,
+            },
+            compute_split(1000, &config(250, RoundingMode::Floor), 0, 3)
+        );
+    }
+
+    // checks if amounts are shown with every decimal of the denom
+    #[test]
+    fn format_decimals_display() {
+        assert_eq!("0.000099", format_decimals(Uint128::new(99), 6));
+        assert_eq!("1.500000", format_decimals(Uint128::new(1_500_000), 6));
+        assert_eq!("0.0", format_decimals(Uint128::zero(), 1));
+        assert_eq!("42", format_decimals(Uint128::new(42), 0));
+    }
+
+    // checks if an odd amount keeps the rounding remainder apart
+    #[test]
+    fn compute_split_odd() {
+        assert_eq!(
+            SplitBreakdown {
+                fee: 2,
+                amount_each: 99,
+                remainder: 1,
+            },
+            compute_split(201, &config(100, RoundingMode::Floor), 0, 2)
+        );
+        assert_eq!(
+            SplitBreakdown {
+                fee: 0,
+                amount_each: 333,
+                remainder: 2,
+            },
+            compute_split(1001, &config(0, RoundingMode::Floor), 0, 3)
+        );
+    }
+
+    // checks if the fee is rounded up as configured and raised to the minimum fee
+    #[test]
+    fn compute_split_fee() {
+        assert_eq!(
+            SplitBreakdown {
+                fee: 3,
+                amount_each: 99,
+                remainder: 0,
+            },
+            compute_split(201, &config(100, RoundingMode::Ceil), 0, 2)
+        );
+        assert_eq!(
+            SplitBreakdown {
+                fee: 10,
+                amount_each: 20,
+                remainder: 0,
+            },
+            compute_split(50, &config(100, RoundingMode::Floor), 10, 2)
+        );
+    }
+
+    // checks if a fee above the amount or no recipients never underflow
+    #[test]
+    fn compute_split_bounds() {
+        assert_eq!(
+            SplitBreakdown {
+                fee: 10,
+                amount_each: 0,
+                remainder: 0,
+            },
+            breakdown_after_fee(10, 20, 2)
+        );
+        assert_eq!(
+            SplitBreakdown {
+                fee: 2,
+                amount_each: 0,
+                remainder: 198,
+            },
+            compute_split(200, &config(100, RoundingMode::Floor), 0, 0)
+        );
+    }
 }
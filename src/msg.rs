@@ -1,33 +1,596 @@
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Coin, Timestamp, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::state::State;
+use crate::state::{AuditEntry, DepositRecord, RoundingMode, State};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {
+    /// Default denom for withdrawals and queries, defaults to usei
+    pub denom: Option<String>,
+    /// Fee collected on every split in basis points, defaults to 100 (1%)
+    pub fee_bps: Option<u16>,
+    /// Minimum amount of each coin a split must carry, defaults to 0
+    pub min_amount: Option<Uint128>,
+    /// Balances in the default denom to seed, e.g. when migrating from an older contract
+    #[serde(default)]
+    pub initial_balances: Vec<(Addr, Uint128)>,
+    /// Number of blocks a sender can reclaim an unwithdrawn deposit, disabled if not set
+    pub reclaim_window: Option<u64>,
+    /// Denoms accepted by splits, defaults to the default denom only
+    pub allowed_denoms: Option<Vec<String>>,
+    /// Address the collected fees are sent to, defaults to the owner
+    pub fee_recipient: Option<Addr>,
+    /// Reject instantiation if the fee recipient is the owner
+    #[serde(default)]
+    pub require_distinct_fee_recipient: bool,
+    /// How the fee is rounded, defaults to floor
+    pub fee_rounding: Option<RoundingMode>,
+    /// Smallest fee a split pays, capped at the amount split, defaults to 0
+    pub min_fee: Option<Uint128>,
+    /// Start with new splits paused until the owner unpauses
+    #[serde(default)]
+    pub start_paused: bool,
+    /// Queue withdrawals until anyone flushes them in a single call instead of sending immediately
+    #[serde(default)]
+    pub batch_payouts: bool,
+    /// Collect the rounding remainders of splits as dust apart from the fees
+    #[serde(default)]
+    pub track_dust: bool,
+    /// Decimals of the default denom for the display amounts in queries, defaults to 6
+    pub decimals: Option<u8>,
+}
+
+/// Message executed on the callback contract of a recipient credited by a split
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CallbackMsg {
+    pub credited: Vec<Coin>,
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct InstantiateMsg {}
+pub struct VestingTerms {
+    /// Part of each share withdrawable immediately in basis points
+    pub immediate_bps: u16,
+    /// Seconds after the split before the rest starts unlocking
+    pub cliff: u64,
+    /// Seconds after the split until the rest is fully unlocked
+    pub duration: u64,
+}
+
+/// What a split does with a payout to an address that fails validation
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InvalidPolicy {
+    /// Fail the whole split
+    #[default]
+    Reject,
+    /// Credit the payout back to the sender
+    RefundSender,
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    /// User can transfer amount to two addresses
-    Split { recipient1: Addr, recipient2: Addr },
+    /// User can transfer amount of every sent coin to two addresses, with an optional reference memo.
+    /// A split with an idempotency key the sender already used is rejected.
+    /// With vesting terms, only part of each share is withdrawable immediately.
+    /// With an expiry, the owner can sweep the balances not withdrawn by then into the fees.
+    /// With the fee on top, the sent amount covers the fee on top of the amount split.
+    /// A coupon replaces the fee rate, without a minimum fee, and is used up by the split.
+    /// An unknown or already used coupon is rejected.
+    /// A nonce has to be the next one of the sender, starting at 0, so that a relayed split
+    /// cannot be replayed.
+    /// With a lock height, nothing of the recipients' balances can be withdrawn before it.
+    /// A referrer is credited the configured cut of the fee
+    Split {
+        recipient1: Addr,
+        recipient2: Addr,
+        memo: Option<String>,
+        idempotency_key: Option<String>,
+        vesting: Option<VestingTerms>,
+        expires_at: Option<Timestamp>,
+        #[serde(default)]
+        fee_on_top: bool,
+        coupon: Option<String>,
+        nonce: Option<u64>,
+        lock_until: Option<u64>,
+        referrer: Option<Addr>,
+    },
+
+    /// User can transfer amount to two addresses after a platform cut in basis points
+    SplitWithPlatform {
+        recipient1: Addr,
+        recipient2: Addr,
+        platform: Addr,
+        platform_bps: u16,
+    },
+
+    /// User can pay exact amounts to several addresses, the payouts plus the fee
+    /// must add up to the single coin sent. An invalid address fails the split
+    /// unless its payout is to be refunded to the sender
+    SplitExact {
+        payouts: Vec<(String, Uint128)>,
+        #[serde(default)]
+        on_invalid: InvalidPolicy,
+    },
+
+    /// User can split their whole withdrawable balance in the default denom to two
+    /// new addresses, without the funds leaving the contract
+    WithdrawAndSplit { recipient1: Addr, recipient2: Addr },
+
+    /// User can keep part of the amount after the fee and tip the rest, in basis points,
+    /// to another address
+    SplitTip { tip_recipient: Addr, tip_bps: u16 },
+
+    /// User can split to recipients given by address or by registered alias, each
+    /// recipient needs exactly one of the two
+    SplitByAlias {
+        recipient1: Option<Addr>,
+        recipient1_alias: Option<String>,
+        recipient2: Option<Addr>,
+        recipient2_alias: Option<String>,
+    },
+
+    /// User can split the amount after the fee between a recipient and their own balance
+    SplitKeepHalf { recipient: Addr },
+
+    /// Anyone can donate a coin of an allowed denom to the fee pot
+    Donate {},
+
+    /// User can withdraw any amount transferred to his address in a denom, the default denom if not set.
+    /// The amount is either an absolute quantity or a percent (1-100) of the balance.
+    /// A delegate can withdraw from the recipient it was set by, the tokens are sent
+    /// to `to` if set, otherwise to the recipient
+    Withdraw {
+        quantity: Option<Uint128>,
+        denom: Option<String>,
+        percent: Option<u8>,
+        from: Option<Addr>,
+        to: Option<Addr>,
+    },
+
+    /// User can withdraw the whole balance of every denom at once
+    WithdrawAll {},
+
+    /// User can withdraw their whole balance in the default denom across several addresses,
+    /// each share in basis points with all shares summing to 10000
+    WithdrawSplit { destinations: Vec<(Addr, u16)> },
+
+    /// Anyone can send the oldest queued withdrawals when payouts are batched, at most
+    /// limit of them (10 by default, 50 at most). A withdrawal whose send fails is credited
+    /// back to its recipient
+    FlushPayouts { limit: Option<u32> },
+
+    /// User can take back their queued withdrawals into their balance before the flush
+    CancelPendingPayout {},
+
+    /// User can register an alias for their own address, the owner for any address
+    RegisterAlias {
+        alias: String,
+        address: Option<Addr>,
+    },
+
+    /// User can register a contract to notify when a split credits them, removed if not set
+    RegisterCallback { contract: Option<Addr> },
+
+    /// User can allow another address to withdraw on their behalf, removed if not set
+    SetWithdrawDelegate { delegate: Option<Addr> },
+
+    /// User can withdraw the whole balance of only the listed denoms, leaving the others credited
+    WithdrawDenoms { denoms: Vec<String> },
+
+    /// Owner can withdraw the dust collected in every denom to their own address
+    WithdrawDust {},
+
+    /// Withdraw fees collected through the transactions to the fee recipient, in every denom
+    /// if neither is set. With a denom or an amount, only the fees of that denom, the default
+    /// denom if not set, are withdrawn: that much of them, or all of them without an amount
+    WithdrawFees {
+        amount: Option<Uint128>,
+        denom: Option<String>,
+    },
+
+    /// Owner can split every fee collected across treasuries,
+    /// each share in basis points with all shares summing to 10000
+    WithdrawFeesSplit { recipients: Vec<(Addr, u16)> },
+
+    /// Sender can take back their latest deposit within the reclaim window
+    Reclaim {},
+
+    /// Owner can limit how much a recipient can withdraw every `window` blocks
+    SetWithdrawLimit { limit: Option<Uint128>, window: u64 },
+
+    /// Owner can make a recipient wait a number of blocks between two withdrawals,
+    /// no wait if 0
+    SetWithdrawCooldown { blocks: u64 },
+
+    /// Owner can set the fee rate in basis points for the splits of one sender
+    SetFeeOverride { sender: Addr, fee_bps: u16 },
+
+    /// Owner can make a sender pay the configured fee rate again
+    ClearFeeOverride { sender: Addr },
+
+    /// Owner can set the fee rate in basis points for the coins of one denom,
+    /// the configured fee rate applies again if not set. A sender's own rate still comes first
+    SetDenomFee { denom: String, fee_bps: Option<u16> },
+
+    /// Owner can set the part of the fee, in basis points, credited to the referrer of a split
+    SetReferrerBps { referrer_bps: u16 },
+
+    /// Owner can create a one-time coupon code with the fee rate in basis points it grants
+    CreateCoupon { code: String, fee_bps: u16 },
+
+    /// Owner can bound the payouts of SplitExact and the destinations of WithdrawSplit,
+    /// at most 50
+    SetMaxRecipients { max: u32 },
 
-    /// User can withdraw any amount transferred to his address
-    Withdraw { quantity: Option<u128> },
+    /// Owner can set the smallest amount a partial withdrawal can take,
+    /// withdrawing a whole balance is always allowed
+    SetMinWithdrawal { min: Uint128 },
 
-    /// Withdraw fees collected through the transactions
-    WithdrawFees {},
+    /// Owner can cap the amount of each coin a split can deposit, no cap if not set
+    SetMaxDeposit { max: Option<Uint128> },
+
+    /// Owner can cap the fee collected per denom, splits forward anything above it
+    /// to the fee recipient. No cap if not set
+    SetFeeAutoForward { threshold: Option<Uint128> },
+
+    /// Owner or an admin can pause and unpause new splits
+    SetPaused { paused: bool },
+
+    /// Owner can halt and resume withdrawals while splits continue
+    SetWithdrawalsFrozen { frozen: bool },
+
+    /// Owner or an admin can change the fee collected on every split in basis points
+    SetFeeRate { fee_bps: u16 },
+
+    /// Owner can let an address pause and change the fee rate
+    AddAdmin { admin: Addr },
+
+    /// Owner can take the admin rights of an address away
+    RemoveAdmin { admin: Addr },
+
+    /// Owner can add to the fees of the default denom whatever the contract holds above
+    /// the balances owed, e.g. tokens sent to it directly. The fees are never lowered
+    ReconcileFees {},
+
+    /// Owner can send the whole contract balance of the default denom to an address while paused
+    EmergencySweep { to: Addr },
+
+    /// Owner can send every balance of an unusable recipient to an address while paused
+    ForceWithdraw { recipient: Addr, to: Addr },
+
+    /// Owner can allow splits in a denom
+    AddDenom { denom: String },
+
+    /// Owner can stop accepting a denom in splits, existing balances remain withdrawable
+    RemoveDenom { denom: String },
+
+    /// Owner can allow a sender to split, which restricts splits to allowed senders only
+    AddSender { sender: Addr },
+
+    /// Owner can stop allowing a sender to split, splits stay restricted even if none are left
+    RemoveSender { sender: Addr },
+
+    /// Owner can block an address from receiving splits and withdrawing its balances
+    BlockAddress { address: Addr },
+
+    /// Owner can unblock a blocked address
+    UnblockAddress { address: Addr },
+
+    /// Owner can remove up to `limit` zero balances, the number removed is set as response data
+    PruneZeroBalances { limit: u32 },
+
+    /// Owner can import balances in the default denom exported from a previous contract,
+    /// only while paused and once per address
+    ImportBalances { entries: Vec<(Addr, Uint128)> },
+
+    /// Owner can move the expired balances of a recipient into the fees
+    SweepExpired { recipient: Addr },
+
+    /// Owner can disable new splits for good, balances and fees can still be withdrawn
+    Finalize {},
+
+    /// Owner can propose a new owner, who takes over once it accepts
+    ProposeOwner { owner: Addr },
+
+    /// The proposed owner accepts the ownership
+    AcceptOwnership {},
+
+    /// Owner can cancel a pending ownership proposal
+    CancelOwnershipProposal {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
+/// Messages called by chain governance, bypassing the owner check
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SudoMsg {
+    /// Change the fee collected on every split in basis points
+    SetFeeRate { fee_bps: u16 },
+
+    /// Pause and unpause new splits
+    SetPaused { paused: bool },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    /// The amount withdrawable by the user
-    WithdrawableAmount { address: Addr },
+    /// The amount withdrawable by the user in a denom, the default denom if not set.
+    /// Amounts still vesting are not included
+    WithdrawableAmount {
+        address: Addr,
+        denom: Option<String>,
+    },
+
+    /// The amounts withdrawable by up to 50 users in a denom, the default denom if not set
+    WithdrawableAmounts {
+        addresses: Vec<Addr>,
+        denom: Option<String>,
+    },
 
     /// Query the owner (creator) of the contract
     OwnerQuery {},
+
+    /// The address proposed as the next owner, if any
+    PendingOwner {},
+
+    /// Activity statistics of the contract
+    Stats {},
+
+    /// Number of balances stored, one per address and denom,
+    /// to estimate the cost of queries iterating over all of them
+    MapSize {},
+
+    /// The owner, denom, fee and allowed denoms configuration of the contract
+    Config {},
+
+    /// Every operational parameter of the contract in one response
+    FullConfig {},
+
+    /// Balances in the default denom after the start_after address, ordered by address,
+    /// in the format ImportBalances accepts
+    ExportBalances {
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+
+    /// The fees collected in every denom
+    TotalFees {},
+
+    /// The balances owed to recipients in every denom
+    TotalLiabilities {},
+
+    /// The rounding remainders collected as dust in every denom
+    Dust {},
+
+    /// The vesting schedule of an address in a denom, the default denom if not set,
+    /// none if nothing is vesting
+    VestingInfo {
+        address: Addr,
+        denom: Option<String>,
+    },
+
+    /// The amount withdrawable by the user in the default denom, raw and in whole tokens
+    DisplayBalance { address: Addr },
+
+    /// The fees collected in the default denom, raw and in whole tokens
+    DisplayFees {},
+
+    /// The nonce the next split of a sender has to carry
+    NextNonce { address: Addr },
+
+    /// Everything ever split to an address in a denom, the default denom if not set
+    TotalEarned {
+        address: Addr,
+        denom: Option<String>,
+    },
+
+    /// Whether splits are paused, withdrawals frozen and the contract finalized
+    Status {},
+
+    /// Whether an address is the owner, an admin, the fee recipient or blocked
+    Roles { address: Addr },
+
+    /// The native balance of the contract in the default denom
+    ContractBalance {},
+
+    /// Compares all balances plus fees of the default denom against the native balance.
+    /// Iterates over every balance, meant for monitoring rather than on-chain use
+    Solvency {},
+
+    /// The coins a withdrawal would send and the balance left afterwards
+    PreviewWithdraw {
+        address: Addr,
+        quantity: Option<Uint128>,
+        denom: Option<String>,
+        percent: Option<u8>,
+    },
+
+    /// The fee and the amount each recipient would get from splitting an amount of the
+    /// default denom between a number of recipients
+    SimulateSplit { amount: Uint128, recipients: u32 },
+
+    /// What withdrawing a quantity of the default denom would send and leave, everything
+    /// if not set. Fails like the withdrawal would, e.g. below the minimum or above the limit
+    SimulateWithdraw {
+        address: Addr,
+        quantity: Option<Uint128>,
+    },
+
+    /// Records of past splits, newest first
+    History {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    /// Fee rate changes as (block height, fee in basis points), oldest first
+    FeeRateHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    /// Splits, withdrawals, fee withdrawals and fee rate changes, oldest first
+    AuditLog {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    /// The distinct recipients a sender has split to, ordered by address
+    RecipientsBySender {
+        sender: Addr,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
 }
 
 pub type OwnerResponse = State;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HistoryResponse {
+    pub records: Vec<(u64, DepositRecord)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ExportBalancesResponse {
+    pub entries: Vec<(Addr, Uint128)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeRateHistoryResponse {
+    pub changes: Vec<(u64, u16)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TotalFeesResponse {
+    pub fees: Vec<Coin>,
+}
+
+/// An amount of the default denom in base units and in whole tokens, e.g. 99 usei as "0.000099"
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DisplayAmount {
+    pub raw: Uint128,
+    pub display: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TotalLiabilitiesResponse {
+    pub liabilities: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AuditLogResponse {
+    pub entries: Vec<(u64, AuditEntry)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RecipientsResponse {
+    pub recipients: Vec<Addr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PreviewWithdrawResponse {
+    pub amount: Vec<Coin>,
+    pub remaining: Uint128,
+}
+
+/// The fee includes the rounding remainder unless the dust is tracked, in which case the
+/// remainder goes to the dust, so that fee + recipients * amount_each == amount, plus the remainder
+/// while the dust is tracked
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateSplitResponse {
+    pub fee: Uint128,
+    pub amount_each: Uint128,
+    pub remainder: Uint128,
+}
+
+/// Set as the response data of a withdrawal
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WithdrawResponse {
+    pub withdrawn: Coin,
+    pub remaining: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub owner: Addr,
+    pub denom: String,
+    pub fee_bps: u16,
+    /// The fee rate as a percentage with two decimals, "1.50" for 150 basis points
+    pub fee_percent: String,
+    pub min_amount: Uint128,
+    pub allowed_denoms: Vec<String>,
+    pub fee_recipient: Addr,
+    pub fee_rounding: RoundingMode,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FullConfigResponse {
+    pub owner: Addr,
+    pub pending_owner: Option<Addr>,
+    pub admins: Vec<Addr>,
+    pub denom: String,
+    pub fee_bps: u16,
+    pub min_fee: Uint128,
+    pub referrer_bps: u16,
+    pub min_amount: Uint128,
+    pub max_deposit: Option<Uint128>,
+    pub allowed_denoms: Vec<String>,
+    pub fee_recipient: Addr,
+    pub fee_rounding: RoundingMode,
+    pub fee_auto_forward_threshold: Option<Uint128>,
+    pub reclaim_window: Option<u64>,
+    pub withdraw_limit: Option<Uint128>,
+    pub withdraw_window: u64,
+    pub withdraw_cooldown: u64,
+    pub min_withdrawal: Uint128,
+    pub max_recipients: u32,
+    pub batch_payouts: bool,
+    pub track_dust: bool,
+    pub decimals: u8,
+    pub paused: bool,
+    pub withdrawals_frozen: bool,
+    pub finalized: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VestingInfoResponse {
+    /// Part of the balance under the vesting, the immediate part is not included
+    pub total: Uint128,
+    pub unlocked: Uint128,
+    pub locked: Uint128,
+    /// Seconds since the epoch
+    pub start: u64,
+    /// Seconds after the start until the vesting is fully unlocked
+    pub duration: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StatusResponse {
+    pub paused: bool,
+    pub withdrawals_frozen: bool,
+    pub finalized: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RolesResponse {
+    pub is_owner: bool,
+    pub is_admin: bool,
+    pub is_fee_recipient: bool,
+    pub is_blocked: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SolvencyResponse {
+    pub liabilities: Uint128,
+    pub holdings: Uint128,
+    pub solvent: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StatsResponse {
+    pub split_count: u64,
+    pub recipient_count: u64,
+}
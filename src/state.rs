@@ -1,7 +1,7 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Coin, Timestamp, Uint128};
 use cw_storage_plus::{Item, Map};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -9,11 +9,260 @@ pub struct State {
     pub owner: Addr,
 }
 
-// address -> withdrawable amount mapping
-pub const AMOUNTS: Map<Addr, u128> = Map::new("amount");
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    Floor,
+    Ceil,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Config {
+    pub denom: String,
+    pub fee_bps: u16,
+    pub min_amount: Uint128,
+    pub fee_recipient: Addr,
+    pub fee_rounding: RoundingMode,
+}
+
+// denom and fee configuration set at instantiation
+pub const CONFIG: Item<Config> = Item::new("config");
+
+// decimals of the default denom, only used to format display amounts in queries
+pub const DECIMALS: Item<u8> = Item::new("decimals");
+
+// denoms accepted by splits
+pub const ALLOWED_DENOMS: Item<Vec<String>> = Item::new("allowed_denoms");
+
+// human-readable alias -> address a split to the alias credits
+pub const ALIASES: Map<String, Addr> = Map::new("aliases");
+
+// recipient -> contract notified every time a split credits the recipient
+pub const CALLBACKS: Map<Addr, Addr> = Map::new("callbacks");
+
+// addresses that can pause and change the fee rate besides the owner
+pub const ADMINS: Item<Vec<Addr>> = Item::new("admins");
+
+// addresses that cannot receive splits or withdraw
+pub const BLOCKED: Map<Addr, ()> = Map::new("blocked");
+
+// senders allowed to split, anyone if not set
+pub const SENDER_ALLOWLIST: Item<Option<Vec<Addr>>> = Item::new("sender_allowlist");
+
+// (address, denom) -> withdrawable amount mapping
+pub const AMOUNTS: Map<(Addr, String), Uint128> = Map::new("amount");
+
+// denom -> total fees collected. The former single-denom fee item used the same
+// namespace but is not read by this map, its fees do not carry over
+pub const FEE_BY_DENOM: Map<String, Uint128> = Map::new("fee");
+
+// whether the rounding remainders of splits go to the dust instead of the fees
+pub const TRACK_DUST: Item<bool> = Item::new("track_dust");
+
+// denom -> rounding remainders of splits collected apart from the fees
+pub const DUST: Map<String, Uint128> = Map::new("dust");
+
+// smallest fee taken on a split
+pub const MIN_FEE: Item<Uint128> = Item::new("min_fee");
+
+// whether new splits are paused
+pub const PAUSED: Item<bool> = Item::new("paused");
+
+// whether withdrawals of balances and fees are halted, splits are not affected
+pub const WITHDRAWALS_FROZEN: Item<bool> = Item::new("withdrawals_frozen");
+
+// whether new splits are disabled for good, withdrawals stay open
+pub const FINALIZED: Item<bool> = Item::new("finalized");
+
+// number of (address, denom) balances stored
+pub const MAP_SIZE: Item<u64> = Item::new("map_size");
+
+// number of successful splits
+pub const SPLIT_COUNT: Item<u64> = Item::new("split_count");
+
+// number of addresses holding a balance in any denom
+pub const RECIPIENT_COUNT: Item<u64> = Item::new("recipient_count");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Deposit {
+    pub recipient1: Addr,
+    pub recipient2: Addr,
+    pub amount: Vec<Coin>,
+    pub height: u64,
+    pub time: Timestamp,
+    // whether the split set the vesting of the recipients
+    #[serde(default)]
+    pub vesting: bool,
+}
+
+// number of blocks a deposit can be reclaimed by its sender
+pub const RECLAIM_WINDOW: Item<Option<u64>> = Item::new("reclaim_window");
+
+// sender -> latest deposit, only recorded when reclaiming is enabled
+pub const DEPOSITS: Map<Addr, Deposit> = Map::new("deposits");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Vesting {
+    pub amount: Uint128,
+    pub start: u64,
+    pub cliff: u64,
+    pub duration: u64,
+}
+
+// (address, denom) -> part of the balance unlocking over time, in seconds
+pub const VESTING: Map<(Addr, String), Vesting> = Map::new("vesting");
+
+// recipient -> address allowed to withdraw on their behalf
+pub const DELEGATES: Map<Addr, Addr> = Map::new("delegates");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingWithdrawal {
+    pub recipient: Addr,
+    pub amount: Vec<Coin>,
+}
+
+// latest withdrawal of a balance or of fees, restored if its bank send fails
+pub const PENDING_WITHDRAWAL: Item<PendingWithdrawal> = Item::new("pending_withdrawal");
+
+// whether withdrawals are queued until flushed instead of sent immediately
+pub const BATCH_PAYOUTS: Item<bool> = Item::new("batch_payouts");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingPayout {
+    pub recipient: Addr,
+    pub to: Addr,
+    pub amount: Vec<Coin>,
+}
+
+// id -> withdrawal already deducted from the balance, waiting for a flush, oldest first
+pub const PENDING_PAYOUTS: Map<u64, PendingPayout> = Map::new("pending_payouts");
 
-// total fees collected
-pub const FEE: Item<u128> = Item::new("fee");
+// id given to the latest queued payout
+pub const PAYOUT_COUNT: Item<u64> = Item::new("payout_count");
+
+// id -> payout sent by the latest flush, credited back to its recipient if its bank send fails
+pub const FLUSHED_PAYOUTS: Map<u64, PendingPayout> = Map::new("flushed_payouts");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WindowWithdrawal {
+    pub start: u64,
+    pub amount: Uint128,
+}
+
+// maximum a recipient can withdraw per window, unlimited if not set
+pub const WITHDRAW_LIMIT: Item<Option<Uint128>> = Item::new("withdraw_limit");
+
+// length of the withdraw limit window in blocks
+pub const WITHDRAW_WINDOW: Item<u64> = Item::new("withdraw_window");
+
+// number of blocks a recipient has to wait between two withdrawals
+pub const WITHDRAW_COOLDOWN: Item<u64> = Item::new("withdraw_cooldown");
+
+// recipient -> block height of its latest withdrawal
+pub const LAST_WITHDRAW_BLOCK: Map<Addr, u64> = Map::new("last_withdraw_block");
+
+// most payouts or destinations a single split can list
+pub const MAX_RECIPIENTS: Item<u32> = Item::new("max_recipients");
+
+// smallest amount a partial withdrawal can take
+pub const MIN_WITHDRAWAL: Item<Uint128> = Item::new("min_withdrawal");
+
+// (address, denom) -> amount withdrawn in the current window
+pub const WITHDRAWN: Map<(Addr, String), WindowWithdrawal> = Map::new("withdrawn");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DepositRecord {
+    pub sender: Addr,
+    pub recipients: Vec<Addr>,
+    pub amount: Vec<Coin>,
+    pub fee: Vec<Coin>,
+    pub height: u64,
+    pub time: Timestamp,
+}
+
+// sender -> fee rate in basis points used instead of the configured one
+pub const FEE_OVERRIDES: Map<Addr, u16> = Map::new("fee_overrides");
+
+// denom -> fee rate in basis points used instead of the configured one for coins of that denom
+pub const DENOM_FEE_BPS: Map<String, u16> = Map::new("denom_fee_bps");
+
+// part of the fee of a split, in basis points, credited to the referrer it names
+pub const REFERRER_BPS: Item<u16> = Item::new("referrer_bps");
+
+// one-time coupon code -> fee rate in basis points replacing the fee of a single split
+pub const COUPONS: Map<String, u16> = Map::new("coupons");
+
+// block height -> fee rate in basis points set at that height
+pub const FEE_RATE_HISTORY: Map<u64, u16> = Map::new("fee_rate_history");
+
+// split count -> record of that split, for auditing
+pub const HISTORY: Map<u64, DepositRecord> = Map::new("history");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AuditEntry {
+    pub action: String,
+    pub actor: Addr,
+    pub amount: Vec<Coin>,
+    pub height: u64,
+}
+
+// number of audit entries, the id of the latest one
+pub const AUDIT_COUNT: Item<u64> = Item::new("audit_count");
+
+// id -> splits, withdrawals, fee withdrawals and fee rate changes in the order they happened
+pub const AUDIT_LOG: Map<u64, AuditEntry> = Map::new("audit_log");
+
+// sender -> nonce its next split has to carry when it carries one
+pub const NONCES: Map<Addr, u64> = Map::new("nonces");
+
+// (sender, idempotency key) -> (), keys of splits already submitted
+pub const SEEN_KEYS: Map<(Addr, String), ()> = Map::new("seen_keys");
+
+// addresses whose balance was imported from a previous contract
+pub const IMPORTED: Map<Addr, ()> = Map::new("imported");
+
+// the part of a balance credited by a split with an expiry, which the owner can sweep
+// into the fees once the expiry has passed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Expiry {
+    pub amount: Uint128,
+    pub expires_at: Timestamp,
+}
+
+// (address, denom) -> the expiring parts of the balance, earliest expiry first. Withdrawals
+// take from them first, so that they never add up to more than the balance
+pub const EXPIRIES: Map<(Addr, String), Vec<Expiry>> = Map::new("expiries");
+
+// the part of a balance credited by a split with a lock, which cannot be withdrawn
+// before the lock height
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Lock {
+    pub amount: Uint128,
+    pub until: u64,
+}
+
+// (address, denom) -> the locked parts of the balance, earliest lock height first.
+// A split only locks its own share, never funds other senders split
+pub const LOCKS: Map<(Addr, String), Vec<Lock>> = Map::new("locks");
+
+// denom -> sum of the balances held in it, kept along every change to the balances
+pub const TOTAL_LIABILITIES: Map<String, Uint128> = Map::new("total_liabilities");
+
+// (address, denom) -> everything ever split to the address, never decreased by withdrawals
+pub const TOTAL_EARNED: Map<(Addr, String), Uint128> = Map::new("total_earned");
+
+// (sender, recipient) -> (), every recipient a sender has split to
+pub const SENDER_RECIPIENTS: Map<(Addr, Addr), ()> = Map::new("sender_recipients");
 
 // State to keep track of owner
 pub const STATE: Item<State> = Item::new("state");
+
+// largest amount of a coin a single split can deposit, no cap if not set
+pub const MAX_DEPOSIT: Item<Option<Uint128>> = Item::new("max_deposit");
+
+// fee pot size per denom above which a split forwards the excess to the fee recipient
+pub const FEE_AUTO_FORWARD_THRESHOLD: Item<Option<Uint128>> =
+    Item::new("fee_auto_forward_threshold");
+
+// address proposed as the next owner, until it accepts
+pub const PENDING_OWNER: Item<Addr> = Item::new("pending_owner");